@@ -1,15 +1,17 @@
 //! This example illustrates how to enable and disable the FPS text in the bottom left hand corner
-//! for a blank screen.
+//! for a blank screen, by clicking the mouse or pressing F12.
 
 use bevy::prelude::*;
 
-use bevy_screen_diags::{ScreenDiagsState, ScreenDiagsTextPlugin};
+use bevy_screen_diags::{ScreenDiagsHotkey, ScreenDiagsState, ScreenDiagsUiPlugin};
 
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
         // Include the plugin
-        .add_plugins(ScreenDiagsTextPlugin)
+        .add_plugins(ScreenDiagsUiPlugin)
+        // Toggle the display with F12
+        .init_resource::<ScreenDiagsHotkey>()
         .add_systems(Startup, setup)
         .add_systems(Update, mouse_handler)
         .run();
@@ -25,10 +27,6 @@ fn mouse_handler(
     mut diags_state: ResMut<ScreenDiagsState>,
 ) {
     if mouse_button_input.just_released(MouseButton::Left) {
-        if diags_state.enabled() {
-            diags_state.disable();
-        } else {
-            diags_state.enable();
-        }
+        diags_state.toggle();
     }
 }
@@ -0,0 +1,39 @@
+//! This example opens a second window, and shows the FPS in the corner of each.
+
+use bevy::{
+    prelude::*, render::camera::RenderTarget, ui::camera_config::UiCameraConfig, window::WindowRef,
+};
+
+use bevy_screen_diags::{ScreenDiagsEveryWindow, ScreenDiagsUiPlugin};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(ScreenDiagsUiPlugin)
+        // Show an overlay in the second window too
+        .init_resource::<ScreenDiagsEveryWindow>()
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
+
+    let second_window = commands
+        .spawn(Window {
+            title: "Second window".to_string(),
+            ..Default::default()
+        })
+        .id();
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                target: RenderTarget::Window(WindowRef::Entity(second_window)),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        // Keep the overlay of the primary window off this one
+        UiCameraConfig { show_ui: false },
+    ));
+}
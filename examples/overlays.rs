@@ -0,0 +1,42 @@
+//! This example shows two overlays: the FPS in the top right hand corner, and the entity count
+//! in the bottom left hand corner, updating twice as often.
+
+use bevy::{diagnostic::EntityCountDiagnosticsPlugin, prelude::*, utils::Duration};
+
+use bevy_screen_diags::{
+    ScreenDiagsConfig, ScreenDiagsOverlay, ScreenDiagsPosition, ScreenDiagsRow, ScreenDiagsStyle,
+    ScreenDiagsUiPlugin,
+};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        // The second overlay needs the entity count diagnostic
+        .add_plugins(EntityCountDiagnosticsPlugin)
+        // Move the first overlay
+        .insert_resource(ScreenDiagsStyle {
+            position: ScreenDiagsPosition::TopRight,
+            fps_colors: vec![(60.0, Color::GREEN), (30.0, Color::YELLOW)],
+            ..Default::default()
+        })
+        .add_plugins(ScreenDiagsUiPlugin)
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
+
+    // Add a second overlay
+    commands.spawn(ScreenDiagsOverlay::new(
+        ScreenDiagsConfig {
+            rows: vec![ScreenDiagsRow::entity_count()],
+            interval: Duration::from_millis(500),
+            ..Default::default()
+        },
+        ScreenDiagsStyle {
+            position: ScreenDiagsPosition::BottomLeft,
+            ..Default::default()
+        },
+    ));
+}
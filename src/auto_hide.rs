@@ -0,0 +1,87 @@
+//! Hiding the display when nothing has needed it for a while.
+
+use bevy::{diagnostic::DiagnosticsStore, prelude::*, utils::Duration};
+
+use crate::{extract_fps, ScreenDiagsConfig, ScreenDiagsVisibilityChanged};
+
+/// Insert this resource to show the display for a few seconds after startup or being enabled,
+/// then fade it out, for release builds where a permanent overlay is unwanted.
+///
+/// The display comes back for as long again when a key is pressed, or while the FPS is below
+/// [wake_below_fps](ScreenDiagsAutoHide::wake_below_fps). It still has to be
+/// [enabled](crate::ScreenDiagsState::enable) to show at all.
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct ScreenDiagsAutoHide {
+    /// How long the display shows before fading out.
+    pub show_for: Duration,
+    /// How long the display takes to fade out.
+    pub fade: Duration,
+    /// Whether a key press shows the display again.
+    pub wake_on_key: bool,
+    /// The FPS below which the display shows again, if any. The FPS is
+    /// [smoothed](ScreenDiagsConfig::smoothing) as the FPS row is.
+    pub wake_below_fps: Option<f64>,
+    // How long the display has been showing since it last woke
+    shown_for: Duration,
+}
+
+impl Default for ScreenDiagsAutoHide {
+    fn default() -> Self {
+        Self::after(Duration::from_secs(5))
+    }
+}
+
+impl ScreenDiagsAutoHide {
+    /// Hides the display this long after it last woke, fading out over a second.
+    pub fn after(show_for: Duration) -> Self {
+        Self {
+            show_for,
+            fade: Duration::from_secs(1),
+            wake_on_key: true,
+            wake_below_fps: None,
+            shown_for: Duration::ZERO,
+        }
+    }
+
+    /// Shows the display again while the FPS is below this.
+    pub fn wake_below_fps(mut self, fps: f64) -> Self {
+        self.wake_below_fps = Some(fps);
+        self
+    }
+
+    /// Shows the display again, for another [show_for](ScreenDiagsAutoHide::show_for).
+    pub fn wake(&mut self) {
+        self.shown_for = Duration::ZERO;
+    }
+
+    /// Whether the display has been hidden for want of attention.
+    pub fn is_asleep(&self) -> bool {
+        self.shown_for >= self.show_for
+    }
+}
+
+// Counts down the time left to show, and wakes the display when it is needed
+pub(crate) fn tick_auto_hide(
+    time: Res<Time>,
+    keyboard: Res<Input<KeyCode>>,
+    diagnostics: Res<DiagnosticsStore>,
+    config: Res<ScreenDiagsConfig>,
+    mut visibility_events: EventReader<ScreenDiagsVisibilityChanged>,
+    mut auto_hide: ResMut<ScreenDiagsAutoHide>,
+) {
+    // Read every event, so none are left over for the next frame
+    let mut enabled = false;
+    for event in visibility_events.iter() {
+        enabled |= event.enabled;
+    }
+    let key_pressed = auto_hide.wake_on_key && keyboard.get_just_pressed().next().is_some();
+    let slow = auto_hide.wake_below_fps.is_some_and(|below| {
+        extract_fps(&diagnostics, config.smoothing).is_some_and(|fps| fps < below)
+    });
+    if enabled || key_pressed || slow {
+        auto_hide.wake();
+    } else if !auto_hide.is_asleep() {
+        auto_hide.shown_for += time.raw_delta();
+    }
+}
@@ -0,0 +1,361 @@
+//! Capturing the frame times over a fixed stretch, as for a scripted fly-through.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, Write as _},
+    path::Path,
+};
+
+use bevy::{prelude::*, utils::Duration};
+
+use crate::{history::percentile, ScreenDiagsMetric};
+
+/// The benchmark resource. Inserting it starts a capture of the frame times, which ends after its
+/// [length](ScreenDiagsBenchmarkLength) with a [BenchmarkReport] event.
+///
+/// The resource is kept when the capture is over, with its [report](ScreenDiagsBenchmark::report),
+/// which a [row](crate::ScreenDiagsRow::benchmark) can show on the screen. Insert a new one to
+/// start another capture.
+#[derive(Resource, Clone, Debug)]
+pub struct ScreenDiagsBenchmark {
+    /// How long to capture for.
+    pub length: ScreenDiagsBenchmarkLength,
+    // The frame times so far, in seconds
+    frame_times: Vec<f64>,
+    elapsed: Duration,
+    report: Option<BenchmarkReport>,
+}
+
+/// How long a [benchmark](ScreenDiagsBenchmark) captures for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScreenDiagsBenchmarkLength {
+    /// Until this much time has passed.
+    Time(Duration),
+    /// Until this many frames have been drawn.
+    Frames(usize),
+}
+
+impl ScreenDiagsBenchmark {
+    /// A benchmark that captures for this long.
+    pub fn new(length: ScreenDiagsBenchmarkLength) -> Self {
+        Self {
+            length,
+            frame_times: Vec::new(),
+            elapsed: Duration::ZERO,
+            report: None,
+        }
+    }
+
+    /// A benchmark that captures for this many seconds. A negative or non-finite number is taken
+    /// as zero, with a warning, which captures a single frame.
+    pub fn seconds(seconds: f32) -> Self {
+        let length = Duration::try_from_secs_f32(seconds).unwrap_or_else(|error| {
+            warn!("Can't benchmark for {} seconds: {}", seconds, error);
+            Duration::ZERO
+        });
+        Self::new(ScreenDiagsBenchmarkLength::Time(length))
+    }
+
+    /// A benchmark that captures this many frames.
+    pub fn frames(frames: usize) -> Self {
+        Self::new(ScreenDiagsBenchmarkLength::Frames(frames))
+    }
+
+    /// Whether the capture is over.
+    pub fn is_finished(&self) -> bool {
+        self.report.is_some()
+    }
+
+    /// The report of the capture, once it is over.
+    pub fn report(&self) -> Option<&BenchmarkReport> {
+        self.report.as_ref()
+    }
+
+    fn is_long_enough(&self) -> bool {
+        match self.length {
+            ScreenDiagsBenchmarkLength::Time(length) => self.elapsed >= length,
+            ScreenDiagsBenchmarkLength::Frames(frames) => self.frame_times.len() >= frames,
+        }
+    }
+
+    // Writes how far the capture has got, or the summary of the report
+    pub(crate) fn write(&self, text: &mut String) {
+        match &self.report {
+            Some(report) => write!(
+                text,
+                "{:.1} FPS, {:.2}/{:.2}/{:.2} ms avg/p50/p99, {:.2} ms sd",
+                report.fps, report.average_ms, report.median_ms, report.p99_ms, report.stddev_ms
+            )
+            .unwrap(),
+            None => match self.length {
+                ScreenDiagsBenchmarkLength::Time(length) => write!(
+                    text,
+                    "capturing {:.0}/{:.0} s",
+                    self.elapsed.as_secs_f64(),
+                    length.as_secs_f64()
+                )
+                .unwrap(),
+                ScreenDiagsBenchmarkLength::Frames(frames) => write!(
+                    text,
+                    "capturing {}/{} frames",
+                    self.frame_times.len(),
+                    frames
+                )
+                .unwrap(),
+            },
+        }
+    }
+}
+
+/// An event sent when a [benchmark](ScreenDiagsBenchmark) is over, with the statistics of the
+/// frame times it captured.
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct BenchmarkReport {
+    /// The number of frames captured.
+    pub frames: usize,
+    /// The time the capture took.
+    pub duration: Duration,
+    /// The average FPS.
+    pub fps: f64,
+    /// The average frame time, in milliseconds.
+    pub average_ms: f64,
+    /// The median frame time, in milliseconds.
+    pub median_ms: f64,
+    /// The frame time that 90% of the frames were quicker than, in milliseconds.
+    pub p90_ms: f64,
+    /// The frame time that 99% of the frames were quicker than, in milliseconds.
+    pub p99_ms: f64,
+    /// The shortest frame time, in milliseconds.
+    pub min_ms: f64,
+    /// The longest frame time, in milliseconds.
+    pub max_ms: f64,
+    /// The standard deviation of the frame times, in milliseconds.
+    pub stddev_ms: f64,
+}
+
+impl BenchmarkReport {
+    /// Writes the report to this file as a flat JSON object, to be
+    /// [loaded](BenchmarkReport::load) later as a [baseline](ScreenDiagsBaseline).
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        writeln!(file, "{{")?;
+        writeln!(file, r#"  "frames": {},"#, self.frames)?;
+        writeln!(
+            file,
+            r#"  "duration_s": {:.6},"#,
+            self.duration.as_secs_f64()
+        )?;
+        for (key, value) in self.fields() {
+            writeln!(file, r#"  "{}": {:.6},"#, key, value)?;
+        }
+        writeln!(file, r#"  "stddev_ms": {:.6}"#, self.stddev_ms)?;
+        writeln!(file, "}}")?;
+        file.flush()
+    }
+
+    /// Reads a report written by [save](BenchmarkReport::save).
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut values = Vec::new();
+        for line in text.lines() {
+            let Some((key, value)) = line.trim().trim_end_matches(',').split_once(':') else {
+                continue;
+            };
+            let value = value.trim().parse::<f64>().map_err(|error| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", key, error))
+            })?;
+            values.push((key.trim().trim_matches('"').to_string(), value));
+        }
+        let field = |name: &str| {
+            values
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|&(_, value)| value)
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, format!("{} is missing", name))
+                })
+        };
+
+        let duration = Duration::try_from_secs_f64(field("duration_s")?).map_err(|error| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("duration_s: {}", error))
+        })?;
+
+        Ok(Self {
+            frames: field("frames")? as usize,
+            duration,
+            fps: field("fps")?,
+            average_ms: field("average_ms")?,
+            median_ms: field("median_ms")?,
+            p90_ms: field("p90_ms")?,
+            p99_ms: field("p99_ms")?,
+            min_ms: field("min_ms")?,
+            max_ms: field("max_ms")?,
+            stddev_ms: field("stddev_ms")?,
+        })
+    }
+
+    // The fields between the duration and the standard deviation, as they are saved
+    fn fields(&self) -> [(&'static str, f64); 7] {
+        [
+            ("fps", self.fps),
+            ("average_ms", self.average_ms),
+            ("median_ms", self.median_ms),
+            ("p90_ms", self.p90_ms),
+            ("p99_ms", self.p99_ms),
+            ("min_ms", self.min_ms),
+            ("max_ms", self.max_ms),
+        ]
+    }
+
+    // The statistics of these frame times, in seconds
+    fn new(frame_times: &mut [f64]) -> Option<Self> {
+        frame_times.sort_unstable_by(f64::total_cmp);
+        let (&shortest, &longest) = (frame_times.first()?, frame_times.last()?);
+        let total: f64 = frame_times.iter().sum();
+        let average = total / frame_times.len() as f64;
+        let variance = frame_times
+            .iter()
+            .map(|frame_time| (frame_time - average).powi(2))
+            .sum::<f64>()
+            / frame_times.len() as f64;
+
+        Some(Self {
+            frames: frame_times.len(),
+            duration: Duration::from_secs_f64(total),
+            fps: frame_times.len() as f64 / total,
+            average_ms: average * 1000.0,
+            median_ms: percentile(frame_times, 0.5) * 1000.0,
+            p90_ms: percentile(frame_times, 0.9) * 1000.0,
+            p99_ms: percentile(frame_times, 0.99) * 1000.0,
+            min_ms: shortest * 1000.0,
+            max_ms: longest * 1000.0,
+            stddev_ms: variance.sqrt() * 1000.0,
+        })
+    }
+}
+
+/// The baseline resource. While it exists, the rows that a [report](BenchmarkReport) has numbers
+/// for show how far the current reading is from the report, such as `FPS: 62 (+4)`.
+///
+/// These are the FPS and frame time rows, and the lowest, average and highest FPS.
+#[derive(Resource, Clone, Debug)]
+pub struct ScreenDiagsBaseline(pub BenchmarkReport);
+
+impl ScreenDiagsBaseline {
+    /// Uses the report [saved](BenchmarkReport::save) in this file as the baseline.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        BenchmarkReport::load(path).map(Self)
+    }
+
+    // Adds the values of the report to compare with the reading of the metric, if there are any
+    pub(crate) fn extend_values(&self, metric: ScreenDiagsMetric, values: &mut Vec<f64>) {
+        let report = &self.0;
+        match metric {
+            ScreenDiagsMetric::Fps => values.push(report.fps),
+            ScreenDiagsMetric::FrameTime => values.push(report.average_ms),
+            ScreenDiagsMetric::FpsMinAvgMax => {
+                values.extend([1000.0 / report.max_ms, report.fps, 1000.0 / report.min_ms])
+            }
+            _ => {}
+        }
+    }
+}
+
+// Adds the last frame to the capture, and sends the report when it is long enough
+pub(crate) fn capture_benchmark(
+    time: Res<Time>,
+    mut benchmark: ResMut<ScreenDiagsBenchmark>,
+    mut reports: EventWriter<BenchmarkReport>,
+) {
+    let frame_time = time.raw_delta_seconds_f64();
+    if benchmark.is_finished() || frame_time <= 0.0 {
+        return;
+    }
+
+    let benchmark = &mut *benchmark;
+    benchmark.frame_times.push(frame_time);
+    benchmark.elapsed += time.raw_delta();
+    if !benchmark.is_long_enough() {
+        return;
+    }
+
+    benchmark.report = BenchmarkReport::new(&mut benchmark.frame_times);
+    benchmark.frame_times = Vec::new();
+    if let Some(report) = &benchmark.report {
+        reports.send(report.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::PathBuf};
+
+    use super::*;
+
+    // A file of this name in the temporary directory, unique to the test process
+    fn temp_file(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("screen_diags_{}_{}.json", std::process::id(), name))
+    }
+
+    #[test]
+    fn takes_bad_lengths_as_zero() {
+        let length = |seconds| ScreenDiagsBenchmark::seconds(seconds).length;
+        let time = ScreenDiagsBenchmarkLength::Time;
+        assert_eq!(length(2.5), time(Duration::from_millis(2500)));
+        assert_eq!(length(0.0), time(Duration::ZERO));
+        assert_eq!(length(-1.0), time(Duration::ZERO));
+        assert_eq!(length(f32::NAN), time(Duration::ZERO));
+        assert_eq!(length(f32::INFINITY), time(Duration::ZERO));
+    }
+
+    #[test]
+    fn loads_the_report_it_saves() {
+        let mut frame_times = vec![0.016, 0.017, 0.015, 0.033, 0.016, 0.0165];
+        let report = BenchmarkReport::new(&mut frame_times).unwrap();
+        let path = temp_file("round_trip");
+        report.save(&path).unwrap();
+        let loaded = ScreenDiagsBaseline::load(&path).unwrap().0;
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.frames, report.frames);
+        let duration_error = loaded.duration.as_secs_f64() - report.duration.as_secs_f64();
+        assert!(duration_error.abs() < 1e-6, "{:?}", loaded.duration);
+        for ((key, loaded), (_, saved)) in loaded.fields().into_iter().zip(report.fields()) {
+            assert!(
+                (loaded - saved).abs() < 1e-6,
+                "{}: {} != {}",
+                key,
+                loaded,
+                saved
+            );
+        }
+        assert!((loaded.stddev_ms - report.stddev_ms).abs() < 1e-6);
+    }
+
+    #[test]
+    fn rejects_a_report_with_a_field_missing() {
+        let path = temp_file("missing_field");
+        fs::write(&path, "{\n  \"frames\": 10,\n  \"duration_s\": 0.5\n}\n").unwrap();
+        let error = BenchmarkReport::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "fps is missing");
+    }
+
+    #[test]
+    fn rejects_a_report_with_a_duration_out_of_range() {
+        let path = temp_file("bad_duration");
+        for duration in ["1e30", "inf", "-1"] {
+            let text = format!(
+                "{{\n  \"frames\": 10,\n  \"duration_s\": {}\n}}\n",
+                duration
+            );
+            fs::write(&path, text).unwrap();
+            let error = BenchmarkReport::load(&path).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(error.to_string().starts_with("duration_s: "), "{}", error);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
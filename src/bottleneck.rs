@@ -0,0 +1,233 @@
+//! A verdict on what holds the frames back: the CPU, the GPU or VSync, judged from the CPU time
+//! of the main world, the GPU time and the frame time.
+
+use bevy::{
+    diagnostic::{
+        Diagnostic, DiagnosticId, Diagnostics, DiagnosticsStore, FrameTimeDiagnosticsPlugin,
+        RegisterDiagnostic,
+    },
+    prelude::*,
+    utils::{Duration, Instant},
+    window::{PresentMode, PrimaryWindow},
+};
+
+use crate::gpu_timings::GPU_TIME;
+
+/// The time the main world took over a frame, in milliseconds, leaving out the wait for the
+/// next frame.
+pub(crate) const CPU_TIME: DiagnosticId =
+    DiagnosticId::from_u128(0x64d1_a8f2_3c9e_4b75_8e06_f2b4_17ca_d935);
+
+/// What holds the frames back, as a [Bottleneck] level.
+pub(crate) const BOTTLENECK: DiagnosticId =
+    DiagnosticId::from_u128(0xe3b9_0f64_72ad_4c18_b5e2_8d47_a1f6_0c93);
+
+// The frames the CPU time is averaged over, as for the GPU time
+const CPU_TIME_HISTORY: usize = 20;
+
+// The share of the frame time a processor must be busy for to be holding the frames back
+const BUSY_SHARE: f64 = 0.85;
+
+// The lower share it must stay busy for to go on holding them back, so the verdict doesn't flicker
+const STAY_BUSY_SHARE: f64 = 0.75;
+
+// How long a new verdict must hold before it is shown
+const HOLD: Duration = Duration::from_secs(1);
+
+/// What holds the frames back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Bottleneck {
+    /// The frames wait for the game's systems or the preparation of the rendering.
+    Cpu,
+    /// The frames wait for the GPU to draw them.
+    Gpu,
+    /// Neither is busy, and the frames wait for the display's refresh.
+    Vsync,
+}
+
+impl Bottleneck {
+    // The level stored in the diagnostic
+    fn level(self) -> f64 {
+        match self {
+            Self::Cpu => 0.0,
+            Self::Gpu => 1.0,
+            Self::Vsync => 2.0,
+        }
+    }
+
+    /// The name of the verdict stored in a diagnostic.
+    pub(crate) fn name(level: f64) -> &'static str {
+        match level as u8 {
+            0 => "CPU-bound",
+            1 => "GPU-bound",
+            _ => "VSync-bound",
+        }
+    }
+}
+
+// When the main world started this frame, and the verdict so far
+#[derive(Resource, Default)]
+struct BottleneckJudge {
+    frame_start: Option<Instant>,
+    verdict: Option<Bottleneck>,
+    // A different verdict, and how long it has held
+    candidate: Option<(Bottleneck, Duration)>,
+}
+
+impl BottleneckJudge {
+    // Judges a frame from the average frame, CPU and GPU times, given whether VSync is on and how
+    // long the frame took, returning the verdict to show
+    fn judge(
+        &mut self,
+        frame: f64,
+        cpu: f64,
+        gpu: f64,
+        vsync: bool,
+        delta: Duration,
+    ) -> Bottleneck {
+        let busy = |processor_time: f64, bottleneck| {
+            let share = if self.verdict == Some(bottleneck) {
+                STAY_BUSY_SHARE
+            } else {
+                BUSY_SHARE
+            };
+            processor_time >= frame * share
+        };
+        let (busiest, busiest_time) = if gpu > cpu {
+            (Bottleneck::Gpu, gpu)
+        } else {
+            (Bottleneck::Cpu, cpu)
+        };
+        let verdict = if busy(busiest_time, busiest) {
+            busiest
+        } else if vsync {
+            Bottleneck::Vsync
+        } else {
+            // Without VSync, the time missing is spent on the render thread
+            Bottleneck::Cpu
+        };
+
+        match self.verdict {
+            None => self.verdict = Some(verdict),
+            Some(shown) if shown == verdict => self.candidate = None,
+            Some(_) => {
+                let held = match self.candidate {
+                    Some((candidate, held)) if candidate == verdict => held + delta,
+                    _ => Duration::ZERO,
+                };
+                self.candidate = Some((verdict, held));
+                if held >= HOLD {
+                    self.verdict = Some(verdict);
+                    self.candidate = None;
+                }
+            }
+        }
+        self.verdict.unwrap_or(verdict)
+    }
+}
+
+pub(crate) fn register(app: &mut App) {
+    app.register_diagnostic(
+        Diagnostic::new(CPU_TIME, "CPU time", CPU_TIME_HISTORY).with_suffix("ms"),
+    )
+    .register_diagnostic(Diagnostic::new(BOTTLENECK, "Bottleneck", 1))
+    .init_resource::<BottleneckJudge>()
+    .add_systems(First, (start_cpu_time, judge_bottleneck))
+    .add_systems(Last, end_cpu_time);
+}
+
+fn start_cpu_time(mut judge: ResMut<BottleneckJudge>) {
+    judge.frame_start = Some(Instant::now());
+}
+
+fn end_cpu_time(mut judge: ResMut<BottleneckJudge>, mut diagnostics: Diagnostics) {
+    if let Some(start) = judge.frame_start.take() {
+        diagnostics.add_measurement(CPU_TIME, || start.elapsed().as_secs_f64() * 1000.0);
+    }
+}
+
+// Judges what held the recent frames back, changing the verdict once a new one has held a while
+fn judge_bottleneck(
+    time: Res<Time>,
+    store: Res<DiagnosticsStore>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut judge: ResMut<BottleneckJudge>,
+    mut diagnostics: Diagnostics,
+) {
+    let average = |id| store.get(id).and_then(|diagnostic| diagnostic.average());
+    let (Some(frame), Some(cpu), Some(gpu)) = (
+        average(FrameTimeDiagnosticsPlugin::FRAME_TIME),
+        average(CPU_TIME),
+        average(GPU_TIME),
+    ) else {
+        return;
+    };
+    let vsync = window_query.get_single().is_ok_and(|window| {
+        matches!(
+            window.present_mode,
+            PresentMode::AutoVsync | PresentMode::Fifo
+        )
+    });
+
+    let verdict = judge.judge(frame, cpu, gpu, vsync, time.raw_delta());
+    diagnostics.add_measurement(BOTTLENECK, || verdict.level());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    #[test]
+    fn blames_the_busiest_processor() {
+        let judge =
+            |cpu, gpu, vsync| BottleneckJudge::default().judge(16.0, cpu, gpu, vsync, FRAME);
+        assert_eq!(judge(15.0, 5.0, true), Bottleneck::Cpu);
+        assert_eq!(judge(5.0, 15.0, true), Bottleneck::Gpu);
+        assert_eq!(judge(13.6, 2.0, false), Bottleneck::Cpu);
+        assert_eq!(judge(5.0, 5.0, true), Bottleneck::Vsync);
+        // Without VSync, the idle time is the render thread's
+        assert_eq!(judge(5.0, 5.0, false), Bottleneck::Cpu);
+    }
+
+    #[test]
+    fn holds_a_new_verdict_for_a_second_before_showing_it() {
+        let mut judge = BottleneckJudge::default();
+        assert_eq!(judge.judge(16.0, 15.0, 5.0, true, FRAME), Bottleneck::Cpu);
+
+        // The first frame of the GPU verdict starts the hold, and the next 62 make 992 ms
+        for _ in 0..63 {
+            assert_eq!(judge.judge(16.0, 5.0, 15.0, true, FRAME), Bottleneck::Cpu);
+        }
+        assert_eq!(judge.judge(16.0, 5.0, 15.0, true, FRAME), Bottleneck::Gpu);
+    }
+
+    #[test]
+    fn restarts_the_hold_when_the_verdict_wavers() {
+        let mut judge = BottleneckJudge::default();
+        judge.judge(16.0, 15.0, 5.0, true, FRAME);
+        for _ in 0..40 {
+            judge.judge(16.0, 5.0, 15.0, true, FRAME);
+        }
+        // A single CPU-bound frame clears the GPU verdict's hold
+        judge.judge(16.0, 15.0, 5.0, true, FRAME);
+        for _ in 0..40 {
+            assert_eq!(judge.judge(16.0, 5.0, 15.0, true, FRAME), Bottleneck::Cpu);
+        }
+    }
+
+    #[test]
+    fn keeps_the_verdict_while_the_processor_stays_fairly_busy() {
+        let mut judge = BottleneckJudge::default();
+        judge.judge(16.0, 5.0, 15.0, true, FRAME);
+        // 80% is below the share to become the verdict, but above the share to stay it
+        for _ in 0..100 {
+            assert_eq!(judge.judge(16.0, 5.0, 12.8, true, FRAME), Bottleneck::Gpu);
+        }
+        assert_eq!(
+            BottleneckJudge::default().judge(16.0, 5.0, 12.8, true, FRAME),
+            Bottleneck::Vsync
+        );
+    }
+}
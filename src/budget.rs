@@ -0,0 +1,183 @@
+//! A bar showing how much of the frame time budget the frames take, drawn next to the text.
+
+use bevy::{prelude::*, utils::Duration};
+
+use crate::{history::ScreenDiagsHistory, overlay::faded_color, ScreenDiagsOverlay, FONT_COLOR};
+
+/// Insert this resource to show a bar that fills with the average frame time in the
+/// [window](crate::ScreenDiagsConfig::window), as a share of a frame time budget, next to the
+/// text. For [overlays](ScreenDiagsOverlay) other than the first, set their budget bar instead.
+///
+/// A marker shows where the budget is reached, so that the headroom left can be seen at a glance.
+/// Frames over budget fill the bar past the marker, in another colour. The bar is redrawn when
+/// the text is.
+#[derive(Resource, Component, Reflect, Clone, Debug)]
+#[reflect(Resource, Component)]
+pub struct ScreenDiagsBudgetBar {
+    /// The frame time budget, where the marker is.
+    pub budget: Duration,
+    /// How far past the marker the bar goes, as a multiple of the budget. Longer frames are cut
+    /// off.
+    pub max: f32,
+    /// The length of the bar up to the marker, in logical pixels.
+    pub width: f32,
+    /// The thickness of the bar, in logical pixels.
+    pub height: f32,
+    /// The colour of the bar within the budget.
+    pub color: Color,
+    /// The colour of the bar over the budget.
+    pub over_color: Color,
+    /// The colour of the track the bar fills, and of the marker.
+    pub track_color: Color,
+}
+
+impl ScreenDiagsBudgetBar {
+    /// A bar for this frame time budget.
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            ..Default::default()
+        }
+    }
+
+    /// A bar for the frame time budget of this frame rate. A frame rate with no frame time, such
+    /// as zero, keeps the default budget, with a warning.
+    pub fn fps(fps: f64) -> Self {
+        match Duration::try_from_secs_f64(1.0 / fps) {
+            Ok(budget) => Self::new(budget),
+            Err(error) => {
+                warn!("Can't budget the frame time of {} FPS: {}", fps, error);
+                Self::default()
+            }
+        }
+    }
+
+    // The share of the budget that a frame time in seconds takes
+    fn fraction(&self, frame_time: f64) -> f32 {
+        (frame_time / self.budget.as_secs_f64()) as f32
+    }
+}
+
+impl Default for ScreenDiagsBudgetBar {
+    fn default() -> Self {
+        Self {
+            budget: Duration::from_micros(16_667),
+            max: 1.5,
+            width: 100.0,
+            height: 8.0,
+            color: Color::GREEN,
+            over_color: Color::RED,
+            track_color: FONT_COLOR.with_a(0.25),
+        }
+    }
+}
+
+// The bar that fills the track
+#[derive(Component)]
+pub(crate) struct BudgetFill;
+
+// The mark where the budget is reached
+#[derive(Component)]
+pub(crate) struct BudgetMarker;
+
+// Adds the budget bar node, with its fill and marker, to an overlay
+pub(crate) fn spawn_budget_bar(parent: &mut ChildBuilder, budget_bar: &ScreenDiagsBudgetBar) {
+    let track = Style {
+        width: Val::Px(budget_bar.width * budget_bar.max.max(1.0)),
+        height: Val::Px(budget_bar.height),
+        align_self: AlignSelf::Center,
+        margin: UiRect::horizontal(Val::Px(budget_bar.height)),
+        ..Default::default()
+    };
+    parent
+        .spawn((
+            NodeBundle {
+                style: track,
+                background_color: budget_bar.track_color.into(),
+                ..Default::default()
+            },
+            budget_bar.clone(),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..Default::default()
+                    },
+                    background_color: budget_bar.color.into(),
+                    ..Default::default()
+                },
+                BudgetFill,
+            ));
+            // The marker stands out past the track on both sides
+            let thickness = (budget_bar.height / 4.0).max(1.0);
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(budget_bar.width - thickness / 2.0),
+                        top: Val::Px(-thickness),
+                        width: Val::Px(thickness),
+                        height: Val::Px(budget_bar.height + thickness * 2.0),
+                        ..Default::default()
+                    },
+                    background_color: budget_bar.track_color.with_a(1.0).into(),
+                    ..Default::default()
+                },
+                BudgetMarker,
+            ));
+        });
+}
+
+// Refills the bar from the history whenever its overlay updates, and fades the whole bar with it
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_budget_bar(
+    history: Res<ScreenDiagsHistory>,
+    overlay_query: Query<(&ScreenDiagsOverlay, &Children)>,
+    mut budget_query: Query<(&ScreenDiagsBudgetBar, &mut BackgroundColor, &Children)>,
+    mut fill_query: Query<
+        (&mut Style, &mut BackgroundColor),
+        (With<BudgetFill>, Without<ScreenDiagsBudgetBar>),
+    >,
+    mut marker_query: Query<
+        &mut BackgroundColor,
+        (
+            With<BudgetMarker>,
+            Without<BudgetFill>,
+            Without<ScreenDiagsBudgetBar>,
+        ),
+    >,
+) {
+    for (overlay, children) in overlay_query.iter() {
+        if !overlay.updated() && !overlay.faded() {
+            continue;
+        }
+
+        let opacity = overlay.opacity();
+        let mut budget_bars = budget_query.iter_many_mut(children.iter());
+        while let Some((budget_bar, mut track_color, parts)) = budget_bars.fetch_next() {
+            track_color.0 = faded_color(budget_bar.track_color, opacity);
+            let mut markers = marker_query.iter_many_mut(parts.iter());
+            while let Some(mut marker_color) = markers.fetch_next() {
+                marker_color.0 = faded_color(budget_bar.track_color.with_a(1.0), opacity);
+            }
+
+            let fraction = history
+                .average_frame_time(1)
+                .map_or(0.0, |frame_time| budget_bar.fraction(frame_time));
+            let max = budget_bar.max.max(1.0);
+            let mut fills = fill_query.iter_many_mut(parts.iter());
+            while let Some((mut style, mut color)) = fills.fetch_next() {
+                style.width = Val::Percent(fraction.min(max) / max * 100.0);
+                let fill_color = if fraction > 1.0 {
+                    budget_bar.over_color
+                } else {
+                    budget_bar.color
+                };
+                color.0 = faded_color(fill_color, opacity);
+            }
+        }
+    }
+}
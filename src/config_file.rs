@@ -0,0 +1,455 @@
+//! The config and style, loaded from a RON asset and applied again whenever it changes.
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+    utils::{BoxedFuture, Duration},
+};
+use serde::Deserialize;
+
+use crate::{
+    is_disabled, ScreenDiagsConfig, ScreenDiagsFpsDisplay, ScreenDiagsPosition, ScreenDiagsRow,
+    ScreenDiagsSmoothing, ScreenDiagsStyle, ScreenDiagsTheme,
+};
+
+/// A plugin that loads the [config](ScreenDiagsConfig) and [style](ScreenDiagsStyle) from a RON
+/// asset, and applies them again whenever the asset changes. Add it as well as the UI plugin.
+///
+/// The file name must end in `.screen_diags.ron`, so that other RON assets are left to their own
+/// loaders. Every setting is optional, and those left out keep their current values.
+///
+/// The changes are only seen while the app runs if the asset server is watching for changes.
+pub struct ScreenDiagsConfigFilePlugin {
+    path: String,
+}
+
+impl ScreenDiagsConfigFilePlugin {
+    /// Loads the settings from this asset path.
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Default for ScreenDiagsConfigFilePlugin {
+    fn default() -> Self {
+        Self::new("screen.screen_diags.ron")
+    }
+}
+
+impl Plugin for ScreenDiagsConfigFilePlugin {
+    fn build(&self, app: &mut App) {
+        if is_disabled(app) {
+            return;
+        }
+        app.add_asset::<ConfigFile>()
+            .init_asset_loader::<ConfigFileLoader>()
+            .insert_resource(ConfigFilePath(self.path.clone()))
+            .add_systems(Startup, load_config_file)
+            .add_systems(Update, apply_config_file);
+    }
+}
+
+// The settings in the file, each of which may be left out
+#[derive(Deserialize, TypeUuid, TypePath, Default)]
+#[uuid = "3c9e4a71-0d5b-4f28-a6e3-81b7d2c05f94"]
+#[serde(default)]
+struct ConfigFile {
+    rows: Option<Vec<RowFile>>,
+    // In seconds
+    interval: Option<f32>,
+    window: Option<f32>,
+    pause_when_unfocused: Option<bool>,
+    smoothing: Option<SmoothingFile>,
+    fps_display: Option<FpsDisplayFile>,
+    // Applied before the other style settings, which override it
+    theme: Option<ThemeFile>,
+    font_size: Option<f32>,
+    color: Option<Color>,
+    position: Option<PositionFile>,
+    // In pixels
+    margin: Option<f32>,
+    fps_colors: Option<Vec<(f64, Color)>>,
+    // A global z-index
+    z_index: Option<i32>,
+}
+
+#[derive(Deserialize)]
+enum RowFile {
+    Fps,
+    FpsTarget(u32),
+    FrameTime,
+    EntityCount,
+    FpsMinAvgMax,
+    FpsLows,
+    FrameCount,
+    Uptime,
+    FixedUpdates,
+    PresentMode,
+    Resolution,
+    CursorPosition,
+    CameraTransform,
+    TrackedPosition,
+    InputState,
+    MeshStats,
+    AssetCounts,
+    EcsCounts,
+    LightCounts,
+    #[cfg(feature = "audio")]
+    Audio,
+    #[cfg(feature = "system-timings")]
+    SlowestSystems,
+    Benchmark,
+    Spikes,
+    FramePacing(u32),
+    FrameTimeJitter,
+    #[cfg(feature = "renet")]
+    NetworkRtt,
+    #[cfg(feature = "renet")]
+    NetworkPacketLoss,
+    #[cfg(feature = "renet")]
+    NetworkBandwidth,
+    #[cfg(feature = "rapier")]
+    PhysicsStep,
+    #[cfg(feature = "rapier")]
+    PhysicsBodies,
+    GpuAdapter,
+    #[cfg(feature = "sysinfo")]
+    Memory,
+    #[cfg(feature = "sysinfo")]
+    Cpu,
+    #[cfg(feature = "linux-device")]
+    Battery,
+    #[cfg(feature = "linux-device")]
+    Thermal,
+    #[cfg(target_arch = "wasm32")]
+    WasmMemory,
+    #[cfg(feature = "gpu-timings")]
+    GpuTime,
+    #[cfg(feature = "gpu-timings")]
+    Bottleneck,
+}
+
+impl From<&RowFile> for ScreenDiagsRow {
+    fn from(row: &RowFile) -> Self {
+        match row {
+            RowFile::Fps => Self::fps(),
+            RowFile::FpsTarget(target) => Self::fps_target(*target),
+            RowFile::FrameTime => Self::frame_time(),
+            RowFile::EntityCount => Self::entity_count(),
+            RowFile::FpsMinAvgMax => Self::fps_min_avg_max(),
+            RowFile::FpsLows => Self::fps_lows(),
+            RowFile::FrameCount => Self::frame_count(),
+            RowFile::Uptime => Self::uptime(),
+            RowFile::FixedUpdates => Self::fixed_updates(),
+            RowFile::PresentMode => Self::present_mode(),
+            RowFile::Resolution => Self::resolution(),
+            RowFile::CursorPosition => Self::cursor_position(),
+            RowFile::CameraTransform => Self::camera_transform(),
+            RowFile::TrackedPosition => Self::tracked_position(),
+            RowFile::InputState => Self::input_state(),
+            RowFile::MeshStats => Self::mesh_stats(),
+            RowFile::AssetCounts => Self::asset_counts(),
+            RowFile::EcsCounts => Self::ecs_counts(),
+            RowFile::LightCounts => Self::light_counts(),
+            #[cfg(feature = "audio")]
+            RowFile::Audio => Self::audio(),
+            #[cfg(feature = "system-timings")]
+            RowFile::SlowestSystems => Self::slowest_systems(),
+            RowFile::Benchmark => Self::benchmark(),
+            RowFile::Spikes => Self::spikes(),
+            RowFile::FramePacing(refresh_rate) => Self::frame_pacing(*refresh_rate),
+            RowFile::FrameTimeJitter => Self::frame_time_jitter(),
+            #[cfg(feature = "renet")]
+            RowFile::NetworkRtt => Self::network_rtt(),
+            #[cfg(feature = "renet")]
+            RowFile::NetworkPacketLoss => Self::network_packet_loss(),
+            #[cfg(feature = "renet")]
+            RowFile::NetworkBandwidth => Self::network_bandwidth(),
+            #[cfg(feature = "rapier")]
+            RowFile::PhysicsStep => Self::physics_step(),
+            #[cfg(feature = "rapier")]
+            RowFile::PhysicsBodies => Self::physics_bodies(),
+            RowFile::GpuAdapter => Self::gpu_adapter(),
+            #[cfg(feature = "sysinfo")]
+            RowFile::Memory => Self::memory(),
+            #[cfg(feature = "sysinfo")]
+            RowFile::Cpu => Self::cpu(),
+            #[cfg(feature = "linux-device")]
+            RowFile::Battery => Self::battery(),
+            #[cfg(feature = "linux-device")]
+            RowFile::Thermal => Self::thermal(),
+            #[cfg(target_arch = "wasm32")]
+            RowFile::WasmMemory => Self::wasm_memory(),
+            #[cfg(feature = "gpu-timings")]
+            RowFile::GpuTime => Self::gpu_time(),
+            #[cfg(feature = "gpu-timings")]
+            RowFile::Bottleneck => Self::bottleneck(),
+        }
+    }
+}
+
+// The positions, with the offsets in pixels
+#[derive(Deserialize, Clone, Copy)]
+enum PositionFile {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Absolute { left: f32, top: f32 },
+}
+
+impl From<PositionFile> for ScreenDiagsPosition {
+    fn from(position: PositionFile) -> Self {
+        match position {
+            PositionFile::TopLeft => Self::TopLeft,
+            PositionFile::TopRight => Self::TopRight,
+            PositionFile::BottomLeft => Self::BottomLeft,
+            PositionFile::BottomRight => Self::BottomRight,
+            PositionFile::Absolute { left, top } => Self::Absolute(UiRect::new(
+                Val::Px(left),
+                Val::Auto,
+                Val::Px(top),
+                Val::Auto,
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+enum SmoothingFile {
+    Average,
+    Window(usize),
+    Exponential(f64),
+}
+
+impl From<SmoothingFile> for ScreenDiagsSmoothing {
+    fn from(smoothing: SmoothingFile) -> Self {
+        match smoothing {
+            SmoothingFile::Average => Self::Average,
+            SmoothingFile::Window(frames) => Self::Window(frames),
+            SmoothingFile::Exponential(alpha) => Self::Exponential(alpha),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+enum FpsDisplayFile {
+    Smoothed,
+    Last,
+    Both,
+}
+
+impl From<FpsDisplayFile> for ScreenDiagsFpsDisplay {
+    fn from(fps_display: FpsDisplayFile) -> Self {
+        match fps_display {
+            FpsDisplayFile::Smoothed => Self::Smoothed,
+            FpsDisplayFile::Last => Self::Last,
+            FpsDisplayFile::Both => Self::Both,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+enum ThemeFile {
+    Dark,
+    Light,
+    HighContrast,
+    Minimal,
+}
+
+impl From<ThemeFile> for ScreenDiagsTheme {
+    fn from(theme: ThemeFile) -> Self {
+        match theme {
+            ThemeFile::Dark => Self::Dark,
+            ThemeFile::Light => Self::Light,
+            ThemeFile::HighContrast => Self::HighContrast,
+            ThemeFile::Minimal => Self::Minimal,
+        }
+    }
+}
+
+impl ConfigFile {
+    fn apply_config(&self, config: &mut ScreenDiagsConfig) {
+        if let Some(rows) = &self.rows {
+            config.rows = rows.iter().map(ScreenDiagsRow::from).collect();
+        }
+        if let Some(interval) = self
+            .interval
+            .and_then(|interval| seconds("interval", interval))
+        {
+            config.interval = interval;
+        }
+        if let Some(window) = self.window.and_then(|window| seconds("window", window)) {
+            config.window = window;
+        }
+        if let Some(pause_when_unfocused) = self.pause_when_unfocused {
+            config.pause_when_unfocused = pause_when_unfocused;
+        }
+        if let Some(smoothing) = self.smoothing {
+            config.smoothing = smoothing.into();
+        }
+        if let Some(fps_display) = self.fps_display {
+            config.fps_display = fps_display.into();
+        }
+    }
+
+    fn apply_style(&self, style: &mut ScreenDiagsStyle) {
+        if let Some(theme) = self.theme {
+            ScreenDiagsTheme::from(theme).apply(style);
+        }
+        if let Some(font_size) = self.font_size {
+            style.font_size = font_size;
+        }
+        if let Some(color) = self.color {
+            style.color = color;
+        }
+        if let Some(position) = self.position {
+            style.position = position.into();
+        }
+        if let Some(margin) = self.margin {
+            style.margin = Val::Px(margin);
+        }
+        if let Some(fps_colors) = &self.fps_colors {
+            style.fps_colors = fps_colors.clone();
+        }
+        if let Some(z_index) = self.z_index {
+            style.z_index = ZIndex::Global(z_index);
+        }
+    }
+
+    fn has_style(&self) -> bool {
+        self.theme.is_some()
+            || self.font_size.is_some()
+            || self.color.is_some()
+            || self.position.is_some()
+            || self.margin.is_some()
+            || self.fps_colors.is_some()
+            || self.z_index.is_some()
+    }
+}
+
+// A duration in seconds from the file, or none with a warning if it can't be one, so that a typo
+// in a hot reload keeps the old value rather than crashing the game
+fn seconds(name: &str, seconds: f32) -> Option<Duration> {
+    match Duration::try_from_secs_f32(seconds) {
+        Ok(duration) => Some(duration),
+        Err(error) => {
+            warn!(
+                "The {} in the diagnostics config file must be a number of seconds, not {}: {}. \
+                The old value is kept.",
+                name, seconds, error
+            );
+            None
+        }
+    }
+}
+
+#[derive(Default)]
+struct ConfigFileLoader;
+
+impl AssetLoader for ConfigFileLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let file = ron::de::from_bytes::<ConfigFile>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(file));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["screen_diags.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct ConfigFilePath(String);
+
+// Keeps the file loaded, so that it is reloaded when it changes
+#[derive(Resource)]
+struct ConfigFileHandle(Handle<ConfigFile>);
+
+fn load_config_file(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    path: Res<ConfigFilePath>,
+) {
+    commands.insert_resource(ConfigFileHandle(asset_server.load(path.0.as_str())));
+}
+
+// Applies the file when it is first loaded, and each time it changes
+fn apply_config_file(
+    mut events: EventReader<AssetEvent<ConfigFile>>,
+    files: Res<Assets<ConfigFile>>,
+    handle: Option<Res<ConfigFileHandle>>,
+    mut config: ResMut<ScreenDiagsConfig>,
+    mut style: ResMut<ScreenDiagsStyle>,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+    for event in events.iter() {
+        let (AssetEvent::Created { handle: changed } | AssetEvent::Modified { handle: changed }) =
+            event
+        else {
+            continue;
+        };
+        if *changed != handle.0 {
+            continue;
+        }
+        let Some(file) = files.get(changed) else {
+            continue;
+        };
+        file.apply_config(&mut config);
+        // Only touch the style when the file sets it, as a change rebuilds the overlay
+        if file.has_style() {
+            file.apply_style(&mut style);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(ron: &str) -> ConfigFile {
+        ron::de::from_str(ron).expect("the config file parses")
+    }
+
+    #[test]
+    fn applies_a_valid_file() {
+        let file = parse("(rows: Some([Fps, FrameTime]), interval: Some(0.5), window: Some(2.0))");
+        let mut config = ScreenDiagsConfig::default();
+        file.apply_config(&mut config);
+        assert_eq!(
+            config.rows,
+            [ScreenDiagsRow::fps(), ScreenDiagsRow::frame_time()]
+        );
+        assert_eq!(config.interval, Duration::from_millis(500));
+        assert_eq!(config.window, Duration::from_secs(2));
+        assert!(!file.has_style());
+    }
+
+    #[test]
+    fn keeps_the_old_durations_in_place_of_bad_ones() {
+        let file = parse("(interval: Some(-1.0), window: Some(inf))");
+        let mut config = ScreenDiagsConfig::default();
+        let (interval, window) = (config.interval, config.window);
+        file.apply_config(&mut config);
+        assert_eq!(config.interval, interval);
+        assert_eq!(config.window, window);
+
+        // Too many seconds for a duration
+        parse("(interval: Some(1e20))").apply_config(&mut config);
+        assert_eq!(config.interval, interval);
+    }
+
+    #[test]
+    fn rejects_an_unknown_row() {
+        assert!(ron::de::from_str::<ConfigFile>("(rows: Some([Fsp]))").is_err());
+    }
+}
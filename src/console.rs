@@ -0,0 +1,170 @@
+//! Console commands for the diagnostics, for games that use bevy_console.
+
+use bevy::{prelude::*, utils::Duration};
+use bevy_console::{reply, reply_failed, AddConsoleCommand, ConsoleCommand};
+use clap::{Parser, Subcommand};
+
+use crate::{
+    is_disabled, ScreenDiagsConfig, ScreenDiagsCorePlugin, ScreenDiagsRow, ScreenDiagsState,
+};
+
+/// A plugin that adds a `diags` command to bevy_console's console.
+///
+/// The command changes the same [state](ScreenDiagsState) and [config](ScreenDiagsConfig) as the
+/// rest of the API:
+/// - `diags on` and `diags off` enable and disable the display.
+/// - `diags interval 0.5` sets the time between updates, in seconds.
+/// - `diags rows fps,frame_time` sets the rows, by the names of their
+///   [constructors](ScreenDiagsRow), with or without underscores. Arguments follow the name, as
+///   in `fps_target_144` or `frame_pacing_60`.
+///
+/// Add it along with bevy_console's `ConsolePlugin`.
+pub struct ScreenDiagsConsolePlugin;
+
+impl Plugin for ScreenDiagsConsolePlugin {
+    fn build(&self, app: &mut App) {
+        if is_disabled(app) {
+            return;
+        }
+        if !app.is_plugin_added::<ScreenDiagsCorePlugin>() {
+            app.add_plugins(ScreenDiagsCorePlugin);
+        }
+        app.add_console_command::<DiagsCommand, _>(diags_command);
+    }
+}
+
+/// Controls the diagnostics display.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "diags")]
+struct DiagsCommand {
+    #[command(subcommand)]
+    action: DiagsAction,
+}
+
+#[derive(Subcommand)]
+enum DiagsAction {
+    /// Shows the display.
+    On,
+    /// Hides the display.
+    Off,
+    /// Sets the time between updates, in seconds.
+    Interval { seconds: f32 },
+    /// Sets the rows, as a list of names separated by commas.
+    Rows { rows: String },
+}
+
+fn diags_command(
+    mut command: ConsoleCommand<DiagsCommand>,
+    mut state: ResMut<ScreenDiagsState>,
+    mut config: ResMut<ScreenDiagsConfig>,
+) {
+    let Some(Ok(DiagsCommand { action })) = command.take() else {
+        return;
+    };
+
+    match action {
+        DiagsAction::On => state.enable(),
+        DiagsAction::Off => state.disable(),
+        DiagsAction::Interval { seconds } => {
+            let Some(interval) = Duration::try_from_secs_f32(seconds)
+                .ok()
+                .filter(|interval| !interval.is_zero())
+            else {
+                reply_failed!(
+                    command,
+                    "The interval must be a number of seconds more than zero"
+                );
+                return;
+            };
+            config.interval = interval;
+        }
+        DiagsAction::Rows { rows } => {
+            let mut new_rows = Vec::new();
+            for name in rows
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+            {
+                match row_named(name) {
+                    Some(row) => new_rows.push(row),
+                    None => {
+                        reply_failed!(command, "There is no row called {}", name);
+                        return;
+                    }
+                }
+            }
+            config.rows = new_rows;
+        }
+    }
+    reply!(command, "Done");
+    command.ok();
+}
+
+// The row made by the constructor with this name, ignoring case, underscores and dashes
+fn row_named(name: &str) -> Option<ScreenDiagsRow> {
+    let name: String = name
+        .chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    let row = match name.as_str() {
+        "fps" => ScreenDiagsRow::fps(),
+        "frametime" => ScreenDiagsRow::frame_time(),
+        "entitycount" => ScreenDiagsRow::entity_count(),
+        "fpsminavgmax" => ScreenDiagsRow::fps_min_avg_max(),
+        "fpslows" => ScreenDiagsRow::fps_lows(),
+        "framecount" => ScreenDiagsRow::frame_count(),
+        "uptime" => ScreenDiagsRow::uptime(),
+        "fixedupdates" => ScreenDiagsRow::fixed_updates(),
+        "presentmode" => ScreenDiagsRow::present_mode(),
+        "resolution" => ScreenDiagsRow::resolution(),
+        "cursorposition" => ScreenDiagsRow::cursor_position(),
+        "cameratransform" => ScreenDiagsRow::camera_transform(),
+        "trackedposition" => ScreenDiagsRow::tracked_position(),
+        "inputstate" => ScreenDiagsRow::input_state(),
+        "meshstats" => ScreenDiagsRow::mesh_stats(),
+        "assetcounts" => ScreenDiagsRow::asset_counts(),
+        "ecscounts" => ScreenDiagsRow::ecs_counts(),
+        "lightcounts" => ScreenDiagsRow::light_counts(),
+        #[cfg(feature = "audio")]
+        "audio" => ScreenDiagsRow::audio(),
+        #[cfg(feature = "system-timings")]
+        "slowestsystems" => ScreenDiagsRow::slowest_systems(),
+        "benchmark" => ScreenDiagsRow::benchmark(),
+        "spikes" => ScreenDiagsRow::spikes(),
+        "frametimejitter" => ScreenDiagsRow::frame_time_jitter(),
+        #[cfg(feature = "renet")]
+        "networkrtt" => ScreenDiagsRow::network_rtt(),
+        #[cfg(feature = "renet")]
+        "networkpacketloss" => ScreenDiagsRow::network_packet_loss(),
+        #[cfg(feature = "renet")]
+        "networkbandwidth" => ScreenDiagsRow::network_bandwidth(),
+        #[cfg(feature = "rapier")]
+        "physicsstep" => ScreenDiagsRow::physics_step(),
+        #[cfg(feature = "rapier")]
+        "physicsbodies" => ScreenDiagsRow::physics_bodies(),
+        "gpuadapter" => ScreenDiagsRow::gpu_adapter(),
+        #[cfg(feature = "sysinfo")]
+        "memory" => ScreenDiagsRow::memory(),
+        #[cfg(feature = "sysinfo")]
+        "cpu" => ScreenDiagsRow::cpu(),
+        #[cfg(feature = "linux-device")]
+        "battery" => ScreenDiagsRow::battery(),
+        #[cfg(feature = "linux-device")]
+        "thermal" => ScreenDiagsRow::thermal(),
+        #[cfg(target_arch = "wasm32")]
+        "wasmmemory" => ScreenDiagsRow::wasm_memory(),
+        #[cfg(feature = "gpu-timings")]
+        "gputime" => ScreenDiagsRow::gpu_time(),
+        #[cfg(feature = "gpu-timings")]
+        "bottleneck" => ScreenDiagsRow::bottleneck(),
+        _ => {
+            if let Some(refresh_rate) = name.strip_prefix("framepacing") {
+                return Some(ScreenDiagsRow::frame_pacing(refresh_rate.parse().ok()?));
+            }
+            let target = name.strip_prefix("fpstarget")?.parse().ok()?;
+            ScreenDiagsRow::fps_target(target)
+        }
+    };
+    Some(row)
+}
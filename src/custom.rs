@@ -0,0 +1,103 @@
+//! Custom rows, whose text is written by the game.
+
+use bevy::prelude::*;
+
+use crate::{ScreenDiagsLog, ScreenDiagsOverlay, ScreenDiagsState};
+
+type WriteRow = Box<dyn FnMut(&mut World, &mut String) + Send + Sync>;
+
+/// The custom rows resource, holding the functions that write the text of custom rows.
+///
+/// Register a function here, then add a [custom row](crate::ScreenDiagsRow::custom) with the same
+/// name to the rows of the [config](crate::ScreenDiagsConfig) to show it. Custom rows share the
+/// font, layout and update cadence of the other rows, and the function is only called when the
+/// display is updated. It has access to the whole world, so it can count the enemies with
+/// `|world, text| write!(text, "{}", world.query::<&Enemy>().iter(world).count()).unwrap()`, for
+/// example.
+///
+/// The function writes into the text of the row, which is cleared before each call, rather than
+/// returning a new string, so that updates do not allocate once the text has grown.
+#[derive(Resource, Default)]
+pub struct ScreenDiagsRows {
+    rows: Vec<CustomRow>,
+}
+
+struct CustomRow {
+    name: &'static str,
+    write: WriteRow,
+    text: String,
+}
+
+impl ScreenDiagsRows {
+    /// Registers the function that writes the custom row with this name, replacing any function
+    /// already registered for it.
+    pub fn add(
+        &mut self,
+        name: &'static str,
+        write: impl FnMut(&mut World, &mut String) + Send + Sync + 'static,
+    ) -> &mut Self {
+        let row = CustomRow {
+            name,
+            write: Box::new(write),
+            text: String::new(),
+        };
+        match self.rows.iter_mut().find(|existing| existing.name == name) {
+            Some(existing) => *existing = row,
+            None => self.rows.push(row),
+        }
+        self
+    }
+
+    /// Registers the function that writes the custom row with this name, for use when inserting
+    /// the resource.
+    pub fn with(
+        mut self,
+        name: &'static str,
+        write: impl FnMut(&mut World, &mut String) + Send + Sync + 'static,
+    ) -> Self {
+        self.add(name, write);
+        self
+    }
+
+    /// Removes the function that writes the custom row with this name.
+    pub fn remove(&mut self, name: &'static str) {
+        self.rows.retain(|row| row.name != name);
+    }
+
+    // The names and latest text of all the custom rows
+    #[cfg_attr(not(feature = "prometheus"), allow(dead_code))]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        self.rows.iter().map(|row| (row.name, row.text.as_str()))
+    }
+
+    // The latest text of the custom row
+    pub(crate) fn text(&self, name: &str) -> Option<&str> {
+        self.rows
+            .iter()
+            .find(|row| row.name == name)
+            .map(|row| row.text.as_str())
+    }
+}
+
+// Calls the custom row functions, keeping their text for the readings
+pub(crate) fn sample_custom_rows(world: &mut World) {
+    world.resource_scope(|world, mut rows: Mut<ScreenDiagsRows>| {
+        for row in rows.rows.iter_mut() {
+            row.text.clear();
+            (row.write)(world, &mut row.text);
+        }
+    });
+}
+
+// Whether any readings will be taken this frame
+pub(crate) fn custom_rows_due(
+    rows: Res<ScreenDiagsRows>,
+    state: Res<ScreenDiagsState>,
+    overlay_query: Query<&ScreenDiagsOverlay>,
+    log: Option<Res<ScreenDiagsLog>>,
+) -> bool {
+    let shown =
+        state.enabled() && (state.due || overlay_query.iter().any(ScreenDiagsOverlay::updated));
+    let logged = log.is_some_and(|log| log.due());
+    !rows.rows.is_empty() && (shown || logged)
+}
@@ -0,0 +1,178 @@
+//! Readings of the battery and the thermal state of the device, taken every few seconds in a
+//! background task.
+//!
+//! They are read from sysfs, so they are only available on Linux. Android keeps apps out of these
+//! files, and other platforms have no sysfs.
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+    utils::Duration,
+};
+use futures_lite::future;
+
+/// The charge of the battery, as a percentage.
+pub(crate) const BATTERY: DiagnosticId =
+    DiagnosticId::from_u128(0xc2f4_8a1d_07e6_4b39_a5d8_3e91_6f0b_d274);
+
+/// The thermal state of the device, as a [ThermalState] level.
+pub(crate) const THERMAL: DiagnosticId =
+    DiagnosticId::from_u128(0x71ae_5d93_c04b_4f2e_8b67_d1c9_2a5e_f380);
+
+// How often the device is read; the battery and temperature change slowly
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How hot the device is running, from the trip points of its hottest thermal zone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ThermalState {
+    /// Below the temperature at which the device slows down.
+    Nominal,
+    /// Past a passive trip point, where the device slows down to cool off.
+    Throttling,
+    /// Past a hot or critical trip point, where the device may shut down.
+    Critical,
+}
+
+impl ThermalState {
+    // The level stored in the diagnostic
+    fn level(self) -> f64 {
+        match self {
+            Self::Nominal => 0.0,
+            Self::Throttling => 1.0,
+            Self::Critical => 2.0,
+        }
+    }
+
+    /// The name of the state stored in a diagnostic.
+    pub(crate) fn name(level: f64) -> &'static str {
+        match level as u8 {
+            0 => "Nominal",
+            1 => "Throttling",
+            _ => "Critical",
+        }
+    }
+}
+
+/// The refresh timer and the task reading the device.
+#[derive(Resource)]
+pub(crate) struct DeviceInfo {
+    timer: Timer,
+    task: Option<Task<DeviceSample>>,
+}
+
+// What one read of the device found
+struct DeviceSample {
+    battery: Option<f64>,
+    thermal: Option<ThermalState>,
+}
+
+pub(crate) fn register(app: &mut App) {
+    app.register_diagnostic(Diagnostic::new(BATTERY, "Battery", 1).with_suffix("%"))
+        .register_diagnostic(Diagnostic::new(THERMAL, "Thermal", 1))
+        .insert_resource(DeviceInfo {
+            timer: first_read_due(),
+            task: None,
+        });
+}
+
+// A refresh timer that is already due, so that the first read is taken at once
+fn first_read_due() -> Timer {
+    let mut timer = Timer::new(REFRESH_INTERVAL, TimerMode::Repeating);
+    timer.set_elapsed(REFRESH_INTERVAL);
+    timer
+}
+
+/// Collects the last read if it has finished, and starts another every few seconds.
+pub(crate) fn sample_device_info(
+    time: Res<Time>,
+    mut info: ResMut<DeviceInfo>,
+    mut diagnostics: Diagnostics,
+) {
+    if let Some(task) = &mut info.task {
+        let Some(sample) = future::block_on(future::poll_once(task)) else {
+            return;
+        };
+        info.task = None;
+        if let Some(battery) = sample.battery {
+            diagnostics.add_measurement(BATTERY, || battery);
+        }
+        if let Some(thermal) = sample.thermal {
+            diagnostics.add_measurement(THERMAL, || thermal.level());
+        }
+    }
+
+    if !info.timer.tick(time.raw_delta()).just_finished() {
+        return;
+    }
+    info.task = Some(AsyncComputeTaskPool::get().spawn(async {
+        DeviceSample {
+            battery: read_battery(),
+            thermal: read_thermal(),
+        }
+    }));
+}
+
+// The capacity of the first battery power supply
+#[cfg(target_os = "linux")]
+fn read_battery() -> Option<f64> {
+    std::fs::read_dir("/sys/class/power_supply")
+        .ok()?
+        .flatten()
+        .map(|supply| supply.path())
+        .filter(|supply| read_sysfs(&supply.join("type")).as_deref() == Some("Battery"))
+        .find_map(|supply| read_sysfs(&supply.join("capacity"))?.parse().ok())
+}
+
+// The state of the hottest thermal zone, judged against its own trip points
+#[cfg(target_os = "linux")]
+fn read_thermal() -> Option<ThermalState> {
+    std::fs::read_dir("/sys/class/thermal")
+        .ok()?
+        .flatten()
+        .map(|zone| zone.path())
+        .filter_map(|zone| {
+            let temp: i64 = read_sysfs(&zone.join("temp"))?.parse().ok()?;
+            // A zone without trip points cannot say whether it is too hot
+            let mut state = None;
+            for trip in 0.. {
+                let trip_point =
+                    |suffix| read_sysfs(&zone.join(format!("trip_point_{trip}_{suffix}")));
+                let (Some(kind), Some(trip_temp)) = (trip_point("type"), trip_point("temp")) else {
+                    break;
+                };
+                let trip_state = match kind.as_str() {
+                    "passive" => ThermalState::Throttling,
+                    "hot" | "critical" => ThermalState::Critical,
+                    _ => continue,
+                };
+                let current = state.get_or_insert(ThermalState::Nominal);
+                if trip_temp
+                    .parse()
+                    .is_ok_and(|trip_temp: i64| temp >= trip_temp)
+                {
+                    *current = trip_state.max(*current);
+                }
+            }
+            state
+        })
+        .max()
+}
+
+// The trimmed contents of a sysfs file
+#[cfg(target_os = "linux")]
+fn read_sysfs(path: &std::path::Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|contents| contents.trim().to_owned())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_battery() -> Option<f64> {
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_thermal() -> Option<ThermalState> {
+    None
+}
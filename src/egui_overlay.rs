@@ -0,0 +1,181 @@
+//! The diagnostics drawn with egui, for apps that use it for all their debug UI.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    is_disabled, overlay::write_row, ScreenDiagsConfig, ScreenDiagsCorePlugin,
+    ScreenDiagsDisplayMode, ScreenDiagsPosition, ScreenDiagsReadings, ScreenDiagsRowsConfig,
+    ScreenDiagsScale, ScreenDiagsSet, ScreenDiagsState, ScreenDiagsStyle,
+};
+
+/// A plugin to draw the diagnostics in an egui area, instead of with Bevy UI.
+///
+/// Add it instead of [ScreenDiagsUiPlugin](crate::ScreenDiagsUiPlugin), along with the
+/// `EguiPlugin`. It shows the rows of the [config](ScreenDiagsConfig) with the
+/// [style](ScreenDiagsStyle), apart from the font, which is egui's own.
+///
+/// Sizes in percentages and viewport units are taken from the size of the screen, as for a Bevy
+/// UI overlay at the root. An egui area is in no layout, so the
+/// [inline](ScreenDiagsPosition::Inline) position is drawn in the top left corner, with a warning.
+pub struct ScreenDiagsEguiPlugin;
+
+impl Plugin for ScreenDiagsEguiPlugin {
+    fn build(&self, app: &mut App) {
+        if is_disabled(app) {
+            return;
+        }
+        if !app.is_plugin_added::<ScreenDiagsCorePlugin>() {
+            app.add_plugins(ScreenDiagsCorePlugin);
+        }
+        app.init_resource::<ScreenDiagsStyle>()
+            .add_systems(Update, draw_diags.in_set(ScreenDiagsSet::Display));
+    }
+}
+
+// The settings saying what is drawn and how
+#[derive(SystemParam)]
+struct Settings<'w> {
+    state: Res<'w, ScreenDiagsState>,
+    mode: Res<'w, ScreenDiagsDisplayMode>,
+    config: Res<'w, ScreenDiagsConfig>,
+    rows_config: Res<'w, ScreenDiagsRowsConfig>,
+    style: Res<'w, ScreenDiagsStyle>,
+    scale: Res<'w, ScreenDiagsScale>,
+}
+
+// Draws the latest readings, which egui needs every frame
+fn draw_diags(
+    mut contexts: EguiContexts,
+    time: Res<Time>,
+    settings: Settings,
+    readings: Res<ScreenDiagsReadings>,
+    mut line: Local<String>,
+    mut warned_inline: Local<bool>,
+) {
+    let Settings {
+        state,
+        mode,
+        config,
+        rows_config,
+        style,
+        scale,
+    } = settings;
+    if !state.enabled() || *mode == ScreenDiagsDisplayMode::Off {
+        return;
+    }
+
+    let ctx = contexts.ctx_mut();
+    let screen = ctx.screen_rect().size();
+    // Across and down the screen, with the pixels of the margin and padding grown by the overlay
+    // scale, but not those of the position
+    let across = |val, scale| resolve(val, screen.x, screen, scale);
+    let down = |val, scale| resolve(val, screen.y, screen, scale);
+    let (margin_x, margin_y) = (
+        across(style.margin, scale.0).unwrap_or(0.0),
+        down(style.margin, scale.0).unwrap_or(0.0),
+    );
+    if style.position == ScreenDiagsPosition::Inline && !*warned_inline {
+        warn!("An egui overlay can't be inline, so it is drawn in the top left corner");
+        *warned_inline = true;
+    }
+    let (align, offset) = match style.position {
+        ScreenDiagsPosition::TopLeft | ScreenDiagsPosition::Inline => {
+            (egui::Align2::LEFT_TOP, [margin_x, margin_y])
+        }
+        ScreenDiagsPosition::TopRight => (egui::Align2::RIGHT_TOP, [-margin_x, margin_y]),
+        ScreenDiagsPosition::BottomLeft => (egui::Align2::LEFT_BOTTOM, [margin_x, -margin_y]),
+        ScreenDiagsPosition::BottomRight => (egui::Align2::RIGHT_BOTTOM, [-margin_x, -margin_y]),
+        // From the left and top unless only the right or bottom are set
+        ScreenDiagsPosition::Absolute(rect) => {
+            let (x_align, x) = match (across(rect.left, 1.0), across(rect.right, 1.0)) {
+                (None, Some(right)) => (egui::Align::RIGHT, -right),
+                (left, _) => (egui::Align::LEFT, left.unwrap_or(0.0)),
+            };
+            let (y_align, y) = match (down(rect.top, 1.0), down(rect.bottom, 1.0)) {
+                (None, Some(bottom)) => (egui::Align::BOTTOM, -bottom),
+                (top, _) => (egui::Align::TOP, top.unwrap_or(0.0)),
+            };
+            (egui::Align2([x_align, y_align]), [x, y])
+        }
+    };
+    let area = egui::Area::new("screen_diags")
+        .interactable(false)
+        .anchor(align, offset);
+    let mut frame = egui::Frame::none();
+    if let Some(panel) = style.panel {
+        // Padding in percentages is of the width, as in Bevy UI
+        let padding = |val| across(val, scale.0).unwrap_or(0.0);
+        frame = frame.fill(color32(panel.color)).inner_margin(egui::Margin {
+            left: padding(panel.padding.left),
+            right: padding(panel.padding.right),
+            top: padding(panel.padding.top),
+            bottom: padding(panel.padding.bottom),
+        });
+    }
+
+    let mut show_rows = |ui: &mut egui::Ui| {
+        let rows = mode.rows(&config.rows).iter();
+        for row in rows.filter(|row| rows_config.is_shown(row.metric)) {
+            line.clear();
+            write_row(&mut line, 0, row, &readings);
+            let color = style
+                .row_color(row.metric, &readings, time.raw_elapsed_seconds_f64())
+                .unwrap_or(style.color);
+            ui.label(
+                egui::RichText::new(line.as_str())
+                    .size(style.font_size * scale.0)
+                    .color(color32(color)),
+            );
+        }
+    };
+    area.show(ctx, |ui| {
+        frame.show(ui, |ui| {
+            if *mode == ScreenDiagsDisplayMode::Compact {
+                ui.horizontal(show_rows);
+            } else {
+                show_rows(ui);
+            }
+        });
+    });
+}
+
+// The size in egui points of a value along a side of the screen this long, with pixels multiplied
+// by the scale, or `None` for `Auto`
+fn resolve(val: Val, length: f32, screen: egui::Vec2, scale: f32) -> Option<f32> {
+    let fraction = |percent: f32, length: f32| Some(length * percent / 100.0);
+    match val {
+        Val::Auto => None,
+        Val::Px(px) => Some(px * scale),
+        Val::Percent(percent) => fraction(percent, length),
+        Val::Vw(percent) => fraction(percent, screen.x),
+        Val::Vh(percent) => fraction(percent, screen.y),
+        Val::VMin(percent) => fraction(percent, screen.min_elem()),
+        Val::VMax(percent) => fraction(percent, screen.max_elem()),
+    }
+}
+
+fn color32(color: Color) -> egui::Color32 {
+    let [red, green, blue, alpha] = color.as_rgba_u8();
+    egui::Color32::from_rgba_unmultiplied(red, green, blue, alpha)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_sizes_against_the_screen() {
+        let screen = egui::vec2(800.0, 600.0);
+        let across = |val| resolve(val, screen.x, screen, 2.0);
+        let down = |val| resolve(val, screen.y, screen, 2.0);
+        assert_eq!(across(Val::Auto), None);
+        assert_eq!(across(Val::Px(10.0)), Some(20.0));
+        assert_eq!(across(Val::Percent(10.0)), Some(80.0));
+        assert_eq!(down(Val::Percent(10.0)), Some(60.0));
+        assert_eq!(down(Val::Vw(50.0)), Some(400.0));
+        assert_eq!(across(Val::Vh(50.0)), Some(300.0));
+        assert_eq!(across(Val::VMin(10.0)), Some(60.0));
+        assert_eq!(down(Val::VMax(10.0)), Some(80.0));
+    }
+}
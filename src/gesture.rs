@@ -0,0 +1,235 @@
+//! Toggling the display with a touch gesture, for phones and tablets without a keyboard.
+
+use bevy::{input::touch::Touches, prelude::*, utils::Duration};
+
+use crate::ScreenDiagsToggle;
+
+/// Insert this resource to toggle the FPS display with a multi-finger tap gesture, by default a
+/// triple tap with three fingers.
+///
+/// A tap counts once all of the fingers have left the screen, if they were lifted within
+/// [max_duration](ScreenDiagsTouchGesture::max_duration) of the last one touching it, without
+/// moving further than [max_movement](ScreenDiagsTouchGesture::max_movement). The taps must
+/// follow each other within [interval](ScreenDiagsTouchGesture::interval).
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct ScreenDiagsTouchGesture {
+    /// The number of fingers touching the screen at once in each tap.
+    pub fingers: usize,
+    /// The number of taps in the gesture.
+    pub taps: usize,
+    /// The longest time between one tap and the next.
+    pub interval: Duration,
+    /// The longest time the fingers can stay on the screen in a tap, so that a long press is not
+    /// taken for one.
+    pub max_duration: Duration,
+    /// The furthest any finger can move in a tap, in logical pixels, so that a swipe or pinch is
+    /// not taken for one.
+    pub max_movement: f32,
+    // The taps so far, and when the last one was
+    tapped: usize,
+    last_tap: Duration,
+    // When all the fingers of the current tap were down, if one is under way
+    tap_start: Option<Duration>,
+    // Whether the current tap has gone on too long or moved too far to count
+    spoiled: bool,
+}
+
+impl Default for ScreenDiagsTouchGesture {
+    fn default() -> Self {
+        Self::new(3, 3)
+    }
+}
+
+impl ScreenDiagsTouchGesture {
+    /// A gesture of this many taps with this many fingers, each within half a second of the last.
+    pub fn new(fingers: usize, taps: usize) -> Self {
+        Self {
+            fingers,
+            taps,
+            interval: Duration::from_millis(500),
+            max_duration: Duration::from_millis(300),
+            max_movement: 20.0,
+            tapped: 0,
+            last_tap: Duration::ZERO,
+            tap_start: None,
+            spoiled: false,
+        }
+    }
+}
+
+// Counts the taps with enough fingers, and sends a toggle event when the gesture is complete
+pub(crate) fn toggle_on_gesture(
+    time: Res<Time>,
+    touches: Option<Res<Touches>>,
+    mut gesture: ResMut<ScreenDiagsTouchGesture>,
+    mut events: EventWriter<ScreenDiagsToggle>,
+) {
+    // Missing without the input plugin
+    let Some(touches) = touches else {
+        return;
+    };
+    if gesture.fingers == 0 {
+        return;
+    }
+    let now = time.raw_elapsed();
+
+    // Only the touch that brings the count up to the fingers starts a tap
+    let touching = touches.iter().count();
+    let new = touches.iter_just_pressed().count();
+    if new > 0 && touching >= gesture.fingers && touching - new < gesture.fingers {
+        gesture.tap_start = Some(now);
+        gesture.spoiled = false;
+    }
+    let Some(start) = gesture.tap_start else {
+        return;
+    };
+    let moved = touches
+        .iter()
+        .chain(touches.iter_just_released())
+        .any(|touch| touch.distance().length() > gesture.max_movement);
+    if moved || touches.any_just_canceled() || now - start > gesture.max_duration {
+        gesture.spoiled = true;
+    }
+    if touching > 0 {
+        return;
+    }
+
+    // Every finger has lifted, which ends the tap
+    gesture.tap_start = None;
+    if gesture.spoiled {
+        gesture.tapped = 0;
+        return;
+    }
+    if gesture.tapped > 0 && now - gesture.last_tap > gesture.interval {
+        gesture.tapped = 0;
+    }
+    gesture.tapped += 1;
+    gesture.last_tap = now;
+    if gesture.tapped >= gesture.taps {
+        gesture.tapped = 0;
+        events.send(ScreenDiagsToggle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        input::touch::{touch_screen_input_system, TouchInput, TouchPhase},
+        time::{TimePlugin, TimeUpdateStrategy},
+    };
+
+    use super::*;
+
+    // The time each update takes
+    const STEP: Duration = Duration::from_millis(50);
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(TimePlugin)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(STEP))
+            .add_event::<TouchInput>()
+            .add_event::<ScreenDiagsToggle>()
+            .init_resource::<Touches>()
+            .insert_resource(ScreenDiagsTouchGesture::new(2, 2))
+            .add_systems(PreUpdate, touch_screen_input_system)
+            .add_systems(Update, toggle_on_gesture);
+        app.update();
+        app
+    }
+
+    fn touch(app: &mut App, id: u64, phase: TouchPhase, x: f32) {
+        app.world.send_event(TouchInput {
+            phase,
+            position: Vec2::new(x, 100.0),
+            force: None,
+            id,
+        });
+    }
+
+    // Puts the fingers down, waits this many updates, and lifts them
+    fn tap(app: &mut App, fingers: u64, held: usize) {
+        for id in 0..fingers {
+            touch(app, id, TouchPhase::Started, id as f32 * 50.0);
+        }
+        app.update();
+        for _ in 0..held {
+            app.update();
+        }
+        for id in 0..fingers {
+            touch(app, id, TouchPhase::Ended, id as f32 * 50.0);
+        }
+        app.update();
+    }
+
+    fn toggles(app: &mut App) -> usize {
+        app.world
+            .resource_mut::<Events<ScreenDiagsToggle>>()
+            .drain()
+            .count()
+    }
+
+    #[test]
+    fn toggles_on_the_last_tap() {
+        let mut app = app();
+        tap(&mut app, 2, 0);
+        assert_eq!(toggles(&mut app), 0);
+        tap(&mut app, 2, 1);
+        assert_eq!(toggles(&mut app), 1);
+        tap(&mut app, 2, 0);
+        tap(&mut app, 2, 0);
+        assert_eq!(toggles(&mut app), 1);
+    }
+
+    #[test]
+    fn needs_enough_fingers() {
+        let mut app = app();
+        tap(&mut app, 1, 0);
+        tap(&mut app, 1, 0);
+        assert_eq!(toggles(&mut app), 0);
+        // More fingers than asked for still make a tap
+        tap(&mut app, 3, 0);
+        tap(&mut app, 2, 0);
+        assert_eq!(toggles(&mut app), 1);
+    }
+
+    #[test]
+    fn ignores_a_long_press() {
+        let mut app = app();
+        // Seven updates of 50 ms is past the 300 ms a tap can last
+        tap(&mut app, 2, 6);
+        tap(&mut app, 2, 0);
+        assert_eq!(toggles(&mut app), 0);
+        tap(&mut app, 2, 0);
+        assert_eq!(toggles(&mut app), 1);
+    }
+
+    #[test]
+    fn ignores_a_swipe() {
+        let mut app = app();
+        tap(&mut app, 2, 0);
+        touch(&mut app, 0, TouchPhase::Started, 0.0);
+        touch(&mut app, 1, TouchPhase::Started, 50.0);
+        app.update();
+        touch(&mut app, 0, TouchPhase::Moved, 40.0);
+        touch(&mut app, 1, TouchPhase::Moved, 90.0);
+        app.update();
+        touch(&mut app, 0, TouchPhase::Ended, 40.0);
+        touch(&mut app, 1, TouchPhase::Ended, 90.0);
+        app.update();
+        assert_eq!(toggles(&mut app), 0);
+    }
+
+    #[test]
+    fn starts_over_after_a_pause() {
+        let mut app = app();
+        tap(&mut app, 2, 0);
+        for _ in 0..20 {
+            app.update();
+        }
+        tap(&mut app, 2, 0);
+        assert_eq!(toggles(&mut app), 0);
+        tap(&mut app, 2, 0);
+        assert_eq!(toggles(&mut app), 1);
+    }
+}
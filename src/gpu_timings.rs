@@ -0,0 +1,229 @@
+//! The time the GPU takes over each frame, measured with timestamp queries around the render
+//! graph.
+//!
+//! The queries need the `TIMESTAMP_QUERY` feature of wgpu, which must be asked for in Bevy's
+//! `WgpuSettings`. The timestamps are read back a few frames later, so as not to stall the GPU.
+
+use std::sync::{
+    atomic::{AtomicU64, AtomicU8, Ordering},
+    Arc,
+};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    render::{
+        main_graph::node::CAMERA_DRIVER,
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+        render_resource::{Buffer, BufferDescriptor, BufferUsages, MapMode, WgpuFeatures},
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        Render, RenderApp, RenderSet,
+    },
+};
+
+/// The time the GPU took over a frame, in milliseconds.
+pub(crate) const GPU_TIME: DiagnosticId =
+    DiagnosticId::from_u128(0x9b2e_4c17_f8a3_4d06_a1c5_6e3f_d092_7b48);
+
+// The frames the GPU time is averaged over
+const GPU_TIME_HISTORY: usize = 20;
+
+// The latest GPU time, as the bits of an f64 in milliseconds, passed from the render world
+static LATEST_GPU_TIME: AtomicU64 = AtomicU64::new(NO_GPU_TIME);
+
+// The bits of a NaN, meaning there is no new time
+const NO_GPU_TIME: u64 = u64::MAX;
+
+// The number of frames whose timestamps can be waiting to be read back at once
+const READBACKS: usize = 3;
+
+// The size of the two timestamps
+const TIMESTAMPS_SIZE: u64 = 2 * std::mem::size_of::<u64>() as u64;
+
+const BEGIN_NODE: &str = "screen_diags_gpu_begin";
+const END_NODE: &str = "screen_diags_gpu_end";
+
+// The states of a readback buffer
+const FREE: u8 = 0;
+const MAPPING: u8 = 1;
+const MAPPED: u8 = 2;
+
+// A buffer the timestamps of a frame are copied into, to be read back once the GPU is done
+struct Readback {
+    buffer: Buffer,
+    state: Arc<AtomicU8>,
+}
+
+// The queries and buffers in the render world, only made if the device supports timestamps
+#[derive(Resource)]
+struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve: Buffer,
+    readbacks: Vec<Readback>,
+    // The readback the timestamps of this frame are copied into, if one is free
+    current: Option<usize>,
+    // The nanoseconds per tick of the timestamps
+    period: f64,
+}
+
+pub(crate) fn register(app: &mut App) {
+    app.register_diagnostic(
+        Diagnostic::new(GPU_TIME, "GPU time", GPU_TIME_HISTORY).with_suffix("ms"),
+    )
+    .add_systems(First, collect_gpu_time);
+
+    let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+        return;
+    };
+    render_app
+        .add_systems(Render, choose_readback.in_set(RenderSet::Prepare))
+        .add_systems(Render, read_back_timestamps.in_set(RenderSet::Cleanup));
+    let Some(mut graph) = render_app.world.get_resource_mut::<RenderGraph>() else {
+        return;
+    };
+    graph.add_node(BEGIN_NODE, TimestampNode { end: false });
+    graph.add_node(END_NODE, TimestampNode { end: true });
+    graph.add_node_edge(BEGIN_NODE, CAMERA_DRIVER);
+    graph.add_node_edge(CAMERA_DRIVER, END_NODE);
+}
+
+// Makes the queries and buffers once the device is known
+pub(crate) fn finish(app: &mut App) {
+    let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+        return;
+    };
+    let device = render_app.world.resource::<RenderDevice>();
+    if !device.features().contains(WgpuFeatures::TIMESTAMP_QUERY) {
+        warn!(
+            "The GPU time can't be measured without the TIMESTAMP_QUERY feature. Ask for it in \
+            the features of the WgpuSettings, if the GPU supports it."
+        );
+        return;
+    }
+    let query_set = device
+        .wgpu_device()
+        .create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("screen_diags_gpu_timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+    let resolve = device.create_buffer(&BufferDescriptor {
+        label: Some("screen_diags_gpu_resolve"),
+        size: TIMESTAMPS_SIZE,
+        usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let readbacks = (0..READBACKS)
+        .map(|_| Readback {
+            buffer: device.create_buffer(&BufferDescriptor {
+                label: Some("screen_diags_gpu_readback"),
+                size: TIMESTAMPS_SIZE,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            state: Arc::new(AtomicU8::new(FREE)),
+        })
+        .collect();
+    let period = f64::from(
+        render_app
+            .world
+            .resource::<RenderQueue>()
+            .get_timestamp_period(),
+    );
+    render_app.insert_resource(GpuTimer {
+        query_set,
+        resolve,
+        readbacks,
+        current: None,
+        period,
+    });
+}
+
+// Writes a timestamp before or after the cameras are drawn
+struct TimestampNode {
+    end: bool,
+}
+
+impl Node for TimestampNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Some(timer) = world.get_resource::<GpuTimer>() else {
+            return Ok(());
+        };
+        let encoder = render_context.command_encoder();
+        if !self.end {
+            encoder.write_timestamp(&timer.query_set, 0);
+            return Ok(());
+        }
+        encoder.write_timestamp(&timer.query_set, 1);
+        // With every readback still in use, this frame goes unmeasured
+        if let Some(current) = timer.current {
+            encoder.resolve_query_set(&timer.query_set, 0..2, &timer.resolve, 0);
+            let readback = &timer.readbacks[current].buffer;
+            encoder.copy_buffer_to_buffer(&timer.resolve, 0, readback, 0, TIMESTAMPS_SIZE);
+        }
+        Ok(())
+    }
+}
+
+// Picks a free readback for the timestamps of this frame
+fn choose_readback(timer: Option<ResMut<GpuTimer>>) {
+    let Some(mut timer) = timer else {
+        return;
+    };
+    timer.current = timer
+        .readbacks
+        .iter()
+        .position(|readback| readback.state.load(Ordering::Acquire) == FREE);
+}
+
+// Maps the readback of this frame, now that it has been submitted, and reads the times of the
+// earlier frames that the GPU has finished
+fn read_back_timestamps(timer: Option<ResMut<GpuTimer>>, device: Res<RenderDevice>) {
+    let Some(mut timer) = timer else {
+        return;
+    };
+    if let Some(current) = timer.current.take() {
+        let readback = &timer.readbacks[current];
+        readback.state.store(MAPPING, Ordering::Release);
+        let state = readback.state.clone();
+        readback
+            .buffer
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                let next = if result.is_ok() { MAPPED } else { FREE };
+                state.store(next, Ordering::Release);
+            });
+    }
+    device.poll(wgpu::Maintain::Poll);
+
+    for readback in &timer.readbacks {
+        if readback.state.load(Ordering::Acquire) != MAPPED {
+            continue;
+        }
+        let ticks = {
+            let timestamps = readback.buffer.slice(..).get_mapped_range();
+            let timestamp = |index: usize| {
+                let bytes = &timestamps[index * 8..(index + 1) * 8];
+                u64::from_le_bytes(bytes.try_into().expect("a timestamp is 8 bytes"))
+            };
+            timestamp(1).wrapping_sub(timestamp(0))
+        };
+        readback.buffer.unmap();
+        readback.state.store(FREE, Ordering::Release);
+        let millis = ticks as f64 * timer.period / 1_000_000.0;
+        LATEST_GPU_TIME.store(millis.to_bits(), Ordering::Relaxed);
+    }
+}
+
+// Adds the latest time read back from the render world to the diagnostic
+fn collect_gpu_time(mut diagnostics: Diagnostics) {
+    let bits = LATEST_GPU_TIME.swap(NO_GPU_TIME, Ordering::Relaxed);
+    if bits != NO_GPU_TIME {
+        diagnostics.add_measurement(GPU_TIME, || f64::from_bits(bits));
+    }
+}
@@ -0,0 +1,126 @@
+//! A bar graph of the recent frame times, drawn next to the text.
+
+use std::iter;
+
+use bevy::{prelude::*, utils::Duration};
+
+use crate::{history::ScreenDiagsHistory, overlay::faded_color, ScreenDiagsOverlay, FONT_COLOR};
+
+/// Insert this resource to show a bar graph of the recent frame times next to the text.
+/// For [overlays](ScreenDiagsOverlay) other than the first, set their graph instead.
+///
+/// There is one bar per frame, newest on the right, and the graph is redrawn when the text is.
+#[derive(Resource, Component, Reflect, Clone, Debug)]
+#[reflect(Resource, Component)]
+pub struct ScreenDiagsGraph {
+    /// The number of bars, which is the number of frames shown.
+    pub bars: usize,
+    /// The width of each bar, in logical pixels.
+    pub bar_width: f32,
+    /// The height of the graph, in logical pixels.
+    pub height: f32,
+    /// The frame time that fills a bar. Longer frames are cut off.
+    pub max_frame_time: Duration,
+    /// The frame time budgets, shortest first, with the colour for frames that fit within them.
+    /// Each bar takes the colour of the first budget its frame fits within.
+    pub budgets: Vec<(Duration, Color)>,
+    /// The colour of the bars for frames that fit within none of the budgets.
+    pub color: Color,
+}
+
+impl ScreenDiagsGraph {
+    // The colour of the bar for a frame time in seconds
+    fn bar_color(&self, frame_time: f64) -> Color {
+        self.budgets
+            .iter()
+            .find(|(budget, _)| frame_time <= budget.as_secs_f64())
+            .map_or(self.color, |(_, color)| *color)
+    }
+}
+
+impl Default for ScreenDiagsGraph {
+    fn default() -> Self {
+        Self {
+            bars: 60,
+            bar_width: 2.0,
+            height: 32.0,
+            max_frame_time: Duration::from_millis(50),
+            budgets: vec![
+                (Duration::from_micros(16_667), Color::GREEN),
+                (Duration::from_micros(33_333), Color::YELLOW),
+            ],
+            color: FONT_COLOR,
+        }
+    }
+}
+
+// The marker on each bar
+#[derive(Component)]
+pub(crate) struct GraphBar;
+
+// Adds the graph node, with its bars, to an overlay
+pub(crate) fn spawn_graph(parent: &mut ChildBuilder, graph: &ScreenDiagsGraph) {
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    height: Val::Px(graph.height),
+                    align_items: AlignItems::FlexEnd,
+                    margin: UiRect::horizontal(Val::Px(graph.bar_width * 2.0)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            graph.clone(),
+        ))
+        .with_children(|parent| {
+            for _ in 0..graph.bars {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(graph.bar_width),
+                            height: Val::Percent(0.0),
+                            ..Default::default()
+                        },
+                        background_color: graph.color.into(),
+                        ..Default::default()
+                    },
+                    GraphBar,
+                ));
+            }
+        });
+}
+
+// Redraws the bars from the history whenever their overlay updates or fades. Only what differs is
+// written, so that unchanged bars are not marked changed.
+pub(crate) fn update_graph(
+    history: Res<ScreenDiagsHistory>,
+    overlay_query: Query<(&ScreenDiagsOverlay, &Children)>,
+    graph_query: Query<(&ScreenDiagsGraph, &Children)>,
+    mut bar_query: Query<(&mut Style, &mut BackgroundColor), With<GraphBar>>,
+) {
+    for (overlay, children) in overlay_query.iter() {
+        if !overlay.updated() && !overlay.faded() {
+            continue;
+        }
+
+        for (graph, bars) in graph_query.iter_many(children.iter()) {
+            let max_frame_time = graph.max_frame_time.as_secs_f64();
+            // Line up the newest frames with the rightmost bars
+            let frame_times = history.frame_times().rev().chain(iter::repeat(0.0));
+            for (bar, frame_time) in bars.iter().rev().zip(frame_times) {
+                if let Ok((mut style, mut color)) = bar_query.get_mut(*bar) {
+                    let fraction = (frame_time / max_frame_time).min(1.0);
+                    let height = Val::Percent(fraction as f32 * 100.0);
+                    if style.height != height {
+                        style.height = height;
+                    }
+                    let bar_color = faded_color(graph.bar_color(frame_time), overlay.opacity());
+                    if color.0 != bar_color {
+                        color.0 = bar_color;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! A histogram of the recent frame times, drawn as labelled bars next to the text.
+
+use bevy::{prelude::*, utils::Duration};
+
+use crate::{history::ScreenDiagsHistory, overlay::faded_color, ScreenDiagsOverlay, FONT_COLOR};
+
+/// Insert this resource to show a histogram of the frame times in the
+/// [window](crate::ScreenDiagsConfig::window) next to the text. For
+/// [overlays](ScreenDiagsOverlay) other than the first, set their histogram instead.
+///
+/// There is one bar per bucket, labelled with its range, and as long as the share of the frames
+/// whose times fall in it. The histogram is redrawn when the text is.
+#[derive(Resource, Component, Reflect, Clone, Debug)]
+#[reflect(Resource, Component)]
+pub struct ScreenDiagsHistogram {
+    /// The frame times that divide the buckets, shortest first, with the colour of the bar below
+    /// each. The frames longer than the last fall in a bucket of their own.
+    pub bounds: Vec<(Duration, Color)>,
+    /// The colour of the bar for the frames longer than the last bound.
+    pub color: Color,
+    /// The length of a bar holding all of the frames, in logical pixels.
+    pub width: f32,
+    /// The thickness of each bar, in logical pixels.
+    pub bar_height: f32,
+    /// The font size of the labels.
+    pub font_size: f32,
+}
+
+impl Default for ScreenDiagsHistogram {
+    fn default() -> Self {
+        Self {
+            bounds: vec![
+                (Duration::from_millis(8), Color::GREEN),
+                (Duration::from_micros(16_667), Color::GREEN),
+                (Duration::from_micros(33_333), Color::YELLOW),
+            ],
+            color: FONT_COLOR,
+            width: 100.0,
+            bar_height: 8.0,
+            font_size: 12.0,
+        }
+    }
+}
+
+impl ScreenDiagsHistogram {
+    // The number of buckets, one more than the bounds
+    fn buckets(&self) -> usize {
+        self.bounds.len() + 1
+    }
+
+    // The bucket a frame time in seconds falls in, where a frame on a bound falls above it
+    fn bucket(&self, frame_time: f64) -> usize {
+        self.bounds
+            .partition_point(|(bound, _)| bound.as_secs_f64() <= frame_time)
+    }
+
+    // Counts the frames in each bucket, from their frame times in seconds
+    fn count(&self, frame_times: impl Iterator<Item = f64>, counts: &mut Vec<usize>) {
+        counts.clear();
+        counts.resize(self.buckets(), 0);
+        for frame_time in frame_times {
+            counts[self.bucket(frame_time)] += 1;
+        }
+    }
+
+    // The colour of the bar of a bucket
+    fn bar_color(&self, bucket: usize) -> Color {
+        self.bounds
+            .get(bucket)
+            .map_or(self.color, |(_, color)| *color)
+    }
+
+    // The label of a bucket, such as `8-16 ms`
+    fn label(&self, bucket: usize) -> String {
+        // Whole milliseconds, so that 16.7 ms reads as the familiar 16
+        let millis = |index: usize| self.bounds[index].0.as_millis();
+        match bucket {
+            0 => format!("<{} ms", millis(0)),
+            _ if bucket == self.bounds.len() => format!(">{} ms", millis(bucket - 1)),
+            _ => format!("{}-{} ms", millis(bucket - 1), millis(bucket)),
+        }
+    }
+}
+
+// The bars and labels of a histogram, by bucket
+#[derive(Component)]
+pub(crate) struct HistogramNodes {
+    bars: Vec<Entity>,
+    labels: Vec<Entity>,
+}
+
+// Adds the histogram node, with a bar and label per bucket, to an overlay whose sizes are divided
+// by the scale
+pub(crate) fn spawn_histogram(
+    parent: &mut ChildBuilder,
+    histogram: &ScreenDiagsHistogram,
+    text_style: &TextStyle,
+    scale: f32,
+) {
+    let mut nodes = HistogramNodes {
+        bars: Vec::new(),
+        labels: Vec::new(),
+    };
+    let mut histogram_node = parent.spawn(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            margin: UiRect::horizontal(Val::Px(histogram.bar_height)),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    histogram_node.with_children(|parent| {
+        for bucket in 0..histogram.buckets() {
+            let row = Style {
+                align_items: AlignItems::Center,
+                ..Default::default()
+            };
+            parent
+                .spawn(NodeBundle {
+                    style: row,
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    // The track keeps the labels lined up, however long the bars are
+                    let track = Style {
+                        width: Val::Px(histogram.width),
+                        height: Val::Px(histogram.bar_height),
+                        ..Default::default()
+                    };
+                    parent
+                        .spawn(NodeBundle {
+                            style: track,
+                            ..Default::default()
+                        })
+                        .with_children(|parent| {
+                            let bar = parent.spawn(NodeBundle {
+                                style: Style {
+                                    width: Val::Percent(0.0),
+                                    height: Val::Percent(100.0),
+                                    ..Default::default()
+                                },
+                                background_color: histogram.bar_color(bucket).into(),
+                                ..Default::default()
+                            });
+                            nodes.bars.push(bar.id());
+                        });
+                    let label = parent.spawn(
+                        TextBundle::from_section(
+                            histogram.label(bucket),
+                            TextStyle {
+                                font_size: histogram.font_size / scale,
+                                ..text_style.clone()
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::left(Val::Px(histogram.bar_height / 2.0)),
+                            ..Default::default()
+                        }),
+                    );
+                    nodes.labels.push(label.id());
+                });
+        }
+    });
+    histogram_node.insert((histogram.clone(), nodes));
+}
+
+// Redraws the bars from the history, and the labels in the text colour, whenever their overlay
+// updates or fades. Only what differs is written, so that unchanged nodes are not marked changed.
+pub(crate) fn update_histogram(
+    history: Res<ScreenDiagsHistory>,
+    overlay_query: Query<(&ScreenDiagsOverlay, &Children)>,
+    histogram_query: Query<(&ScreenDiagsHistogram, &HistogramNodes)>,
+    mut bar_query: Query<(&mut Style, &mut BackgroundColor)>,
+    mut label_query: Query<&mut Text>,
+    // The frames in each bucket, kept to save allocating
+    mut counts: Local<Vec<usize>>,
+) {
+    for (overlay, children) in overlay_query.iter() {
+        if !overlay.updated() && !overlay.faded() {
+            continue;
+        }
+
+        let opacity = overlay.opacity();
+        for (histogram, nodes) in histogram_query.iter_many(children.iter()) {
+            histogram.count(history.frame_times(), &mut counts);
+            let total = counts.iter().sum::<usize>().max(1);
+            for (bucket, (bar, frames)) in nodes.bars.iter().zip(counts.iter()).enumerate() {
+                let Ok((mut style, mut color)) = bar_query.get_mut(*bar) else {
+                    continue;
+                };
+                let width = Val::Percent(*frames as f32 * 100.0 / total as f32);
+                if style.width != width {
+                    style.width = width;
+                }
+                let bar_color = faded_color(histogram.bar_color(bucket), opacity);
+                if color.0 != bar_color {
+                    color.0 = bar_color;
+                }
+            }
+            let color = faded_color(overlay.style.color, opacity);
+            let mut labels = label_query.iter_many_mut(&nodes.labels);
+            while let Some(mut text) = labels.fetch_next() {
+                if text
+                    .sections
+                    .iter()
+                    .any(|section| section.style.color != color)
+                {
+                    for section in &mut text.sections {
+                        section.style.color = color;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn puts_frames_on_a_bound_in_the_bucket_above() {
+        let histogram = ScreenDiagsHistogram::default();
+        let cases = [
+            (0.0, 0),
+            (0.0079, 0),
+            (0.008, 1),
+            (0.0166, 1),
+            (0.016_667, 2),
+            (0.033, 2),
+            (0.033_333, 3),
+            (1.0, 3),
+        ];
+        for (frame_time, bucket) in cases {
+            assert_eq!(histogram.bucket(frame_time), bucket, "{}", frame_time);
+        }
+    }
+
+    #[test]
+    fn counts_every_frame_in_one_bucket() {
+        let histogram = ScreenDiagsHistogram::default();
+        let mut counts = vec![7; 10];
+        let frame_times = [0.005, 0.01, 0.012, 0.016_667, 0.02, 0.025, 0.03, 0.1];
+        histogram.count(frame_times.into_iter(), &mut counts);
+        assert_eq!(counts, [1, 2, 4, 1]);
+
+        histogram.count(std::iter::empty(), &mut counts);
+        assert_eq!(counts, [0; 4]);
+    }
+
+    #[test]
+    fn labels_the_buckets_in_whole_milliseconds() {
+        let histogram = ScreenDiagsHistogram::default();
+        let labels: Vec<_> = (0..histogram.buckets())
+            .map(|bucket| histogram.label(bucket))
+            .collect();
+        assert_eq!(labels, ["<8 ms", "8-16 ms", "16-33 ms", ">33 ms"]);
+
+        let single = ScreenDiagsHistogram {
+            bounds: vec![(Duration::from_millis(20), Color::GREEN)],
+            ..Default::default()
+        };
+        assert_eq!(single.label(0), "<20 ms");
+        assert_eq!(single.label(1), ">20 ms");
+    }
+}
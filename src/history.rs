@@ -0,0 +1,257 @@
+//! The recent frame times, kept for statistics over a sliding window.
+
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::Duration, window::PrimaryWindow};
+
+use crate::ScreenDiagsConfig;
+
+/// The frames within the [window](ScreenDiagsConfig::window), oldest first, which the overlay
+/// takes its statistics from.
+///
+/// Read this resource to use the same data in other systems, such as for scaling the quality
+/// to the frame rate, or uploading telemetry.
+#[derive(Resource, Default, Debug)]
+pub struct ScreenDiagsHistory {
+    samples: VecDeque<ScreenDiagsFrameSample>,
+    // Scratch space for sorting the frame times, kept to save allocating
+    sorted: Vec<f64>,
+    // Whether frames are being left out while the window is unfocused
+    paused: bool,
+}
+
+/// One frame in the [history](ScreenDiagsHistory).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScreenDiagsFrameSample {
+    /// When the frame ended, in real time since startup.
+    pub time: Duration,
+    /// How long the frame took, in seconds.
+    pub frame_time: f64,
+    /// Whether frames were left out before this one, while the window was
+    /// [unfocused](ScreenDiagsConfig::pause_when_unfocused).
+    pub after_gap: bool,
+}
+
+impl ScreenDiagsHistory {
+    /// Whether frames are being left out, because the window is
+    /// [unfocused](ScreenDiagsConfig::pause_when_unfocused).
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// The frames in the window, oldest first.
+    pub fn samples(&self) -> impl DoubleEndedIterator<Item = &ScreenDiagsFrameSample> + '_ {
+        self.samples.iter()
+    }
+
+    /// The lowest, average and highest FPS in the window.
+    pub fn fps_min_avg_max(&self) -> Option<(f64, f64, f64)> {
+        let frame_times = self.samples.iter().map(|sample| sample.frame_time);
+        let longest = frame_times.clone().reduce(f64::max)?;
+        let shortest = frame_times.clone().reduce(f64::min)?;
+        let total: f64 = frame_times.sum();
+
+        Some((
+            1.0 / longest,
+            self.samples.len() as f64 / total,
+            1.0 / shortest,
+        ))
+    }
+
+    /// The average frame time in the window, in seconds, if there are at least this many frames.
+    pub fn average_frame_time(&self, min_frames: usize) -> Option<f64> {
+        if self.samples.len() < min_frames.max(1) {
+            return None;
+        }
+        let total: f64 = self.samples.iter().map(|sample| sample.frame_time).sum();
+        Some(total / self.samples.len() as f64)
+    }
+
+    /// The standard deviation of the frame times in the window, in seconds.
+    pub fn frame_time_stddev(&self) -> Option<f64> {
+        let average = self.average_frame_time(1)?;
+        let variance = self
+            .samples
+            .iter()
+            .map(|sample| (sample.frame_time - average).powi(2))
+            .sum::<f64>()
+            / self.samples.len() as f64;
+        Some(variance.sqrt())
+    }
+
+    /// The frame times in the window, in seconds, oldest first.
+    pub fn frame_times(&self) -> impl DoubleEndedIterator<Item = f64> + '_ {
+        self.samples.iter().map(|sample| sample.frame_time)
+    }
+
+    /// The number of frames in the window that took longer than this many seconds, and the
+    /// number of frames in the window.
+    pub fn late_frames(&self, longest: f64) -> Option<(usize, usize)> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let late = self
+            .samples
+            .iter()
+            .filter(|sample| sample.frame_time > longest)
+            .count();
+        Some((late, self.samples.len()))
+    }
+
+    /// The 1% and 0.1% low FPS in the window.
+    pub(crate) fn fps_lows(&mut self) -> Option<(f64, f64)> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        self.sorted.clear();
+        self.sorted
+            .extend(self.samples.iter().map(|sample| sample.frame_time));
+        self.sorted.sort_unstable_by(f64::total_cmp);
+
+        Some((
+            1.0 / percentile(&self.sorted, 0.99),
+            1.0 / percentile(&self.sorted, 0.999),
+        ))
+    }
+}
+
+// The value below which the fraction of the sorted values lie
+pub(crate) fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    let index = ((sorted.len() - 1) as f64 * fraction).round() as usize;
+    sorted[index]
+}
+
+// Whether the primary window is in the background, or minimized to nothing, where the OS
+// throttles its frames
+pub(crate) fn is_unfocused(window_query: &Query<&Window, With<PrimaryWindow>>) -> bool {
+    window_query.get_single().is_ok_and(|window| {
+        !window.focused || window.physical_width() == 0 || window.physical_height() == 0
+    })
+}
+
+// Adds the last frame to the history, and forgets the frames that have left the window
+pub(crate) fn record_frame_time(
+    time: Res<Time>,
+    config: Res<ScreenDiagsConfig>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut history: ResMut<ScreenDiagsHistory>,
+) {
+    let frame_time = time.raw_delta_seconds_f64();
+    if frame_time <= 0.0 {
+        return;
+    }
+    if config.pause_when_unfocused && is_unfocused(&window_query) {
+        history.paused = true;
+        return;
+    }
+
+    let now = time.raw_elapsed();
+    let after_gap = std::mem::take(&mut history.paused);
+    history.samples.push_back(ScreenDiagsFrameSample {
+        time: now,
+        frame_time,
+        after_gap,
+    });
+    while history
+        .samples
+        .front()
+        .is_some_and(|oldest| now.saturating_sub(oldest.time) > config.window)
+    {
+        history.samples.pop_front();
+    }
+}
+
+/// The times of the fixed updates in the last second, oldest first.
+#[derive(Resource, Default)]
+pub(crate) struct FixedUpdateHistory {
+    times: VecDeque<Duration>,
+}
+
+impl FixedUpdateHistory {
+    /// The number of fixed updates in the second before this time.
+    pub(crate) fn last_second(&self, now: Duration) -> usize {
+        self.times
+            .iter()
+            .filter(|time| now.saturating_sub(**time) <= Duration::from_secs(1))
+            .count()
+    }
+}
+
+// Adds a fixed update to the history, and forgets those over a second old
+pub(crate) fn record_fixed_update(time: Res<Time>, mut history: ResMut<FixedUpdateHistory>) {
+    let now = time.raw_elapsed();
+    history.times.push_back(now);
+    while history
+        .times
+        .front()
+        .is_some_and(|oldest| now.saturating_sub(*oldest) > Duration::from_secs(1))
+    {
+        history.times.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(frame_times: impl IntoIterator<Item = f64>) -> ScreenDiagsHistory {
+        let mut history = ScreenDiagsHistory::default();
+        for (frame, frame_time) in frame_times.into_iter().enumerate() {
+            history.samples.push_back(ScreenDiagsFrameSample {
+                time: Duration::from_millis(frame as u64),
+                frame_time,
+                after_gap: false,
+            });
+        }
+        history
+    }
+
+    #[test]
+    fn takes_percentiles_of_short_runs() {
+        assert_eq!(percentile(&[4.0], 0.0), 4.0);
+        assert_eq!(percentile(&[4.0], 0.99), 4.0);
+        assert_eq!(percentile(&[1.0, 2.0], 0.0), 1.0);
+        assert_eq!(percentile(&[1.0, 2.0], 0.4), 1.0);
+        assert_eq!(percentile(&[1.0, 2.0], 0.99), 2.0);
+    }
+
+    #[test]
+    fn takes_percentiles_of_a_thousand_values() {
+        let sorted: Vec<f64> = (0..1000).map(f64::from).collect();
+        assert_eq!(percentile(&sorted, 0.0), 0.0);
+        assert_eq!(percentile(&sorted, 0.5), 500.0);
+        assert_eq!(percentile(&sorted, 0.99), 989.0);
+        assert_eq!(percentile(&sorted, 0.999), 998.0);
+        assert_eq!(percentile(&sorted, 1.0), 999.0);
+    }
+
+    #[test]
+    fn finds_the_lows_among_the_slowest_frames() {
+        assert_eq!(history([]).fps_lows(), None);
+
+        // 980 frames at 100 FPS, 15 at 50 FPS and 5 at 10 FPS, out of order
+        let frame_times = (0..1000).map(|frame| match frame % 200 {
+            0 => 0.1,
+            1..=3 => 0.02,
+            _ => 0.01,
+        });
+        let (low_1, low_01) = history(frame_times).fps_lows().unwrap();
+        assert!((low_1 - 50.0).abs() < 1e-9, "{}", low_1);
+        assert!((low_01 - 10.0).abs() < 1e-9, "{}", low_01);
+    }
+
+    #[test]
+    fn measures_the_spread_of_frame_times() {
+        assert_eq!(history([]).frame_time_stddev(), None);
+        let steady = history([0.016; 60]).frame_time_stddev().unwrap();
+        assert!(steady.abs() < 1e-12, "{}", steady);
+        assert_eq!(history([0.01]).frame_time_stddev(), Some(0.0));
+
+        // Alternating between 10 and 30 ms is 10 ms either side of the average
+        let stddev = history([0.01, 0.03, 0.01, 0.03])
+            .frame_time_stddev()
+            .unwrap();
+        assert!((stddev - 0.01).abs() < 1e-12, "{}", stddev);
+    }
+}
@@ -0,0 +1,57 @@
+//! Translating the labels of the rows, for localised builds.
+
+use bevy::{prelude::*, utils::HashMap};
+
+type Translate = Box<dyn Fn(&str, &mut String) + Send + Sync>;
+
+/// The labels resource, which replaces the English labels of the rows, such as `FPS`, with
+/// translations.
+///
+/// The labels are looked up by their English text, which for
+/// [diagnostic](crate::ScreenDiagsRow::diagnostic) rows is the name of the diagnostic. Labels that
+/// have not been inserted are passed to the [translator](ScreenDiagsLabels::with_translator), if
+/// there is one, and otherwise left in English. A [label](crate::ScreenDiagsRow::label) set on a
+/// row is shown as it is.
+#[derive(Resource, Default)]
+pub struct ScreenDiagsLabels {
+    labels: HashMap<String, String>,
+    translate: Option<Translate>,
+}
+
+impl ScreenDiagsLabels {
+    /// Shows this label in place of the English one.
+    pub fn insert(&mut self, english: impl Into<String>, label: impl Into<String>) -> &mut Self {
+        self.labels.insert(english.into(), label.into());
+        self
+    }
+
+    /// Shows this label in place of the English one, for use when inserting the resource.
+    pub fn with(mut self, english: impl Into<String>, label: impl Into<String>) -> Self {
+        self.insert(english, label);
+        self
+    }
+
+    /// Translates the labels that have not been inserted with this function, which writes the
+    /// translation of the English label into the string, or leaves it empty to keep the English.
+    /// It is called whenever the readings are taken, so it should be quick, as a lookup in the
+    /// game's own translations.
+    pub fn with_translator(
+        mut self,
+        translate: impl Fn(&str, &mut String) + Send + Sync + 'static,
+    ) -> Self {
+        self.translate = Some(Box::new(translate));
+        self
+    }
+
+    // Writes the translation of the English label, or nothing if there is none
+    pub(crate) fn translate(&self, english: &str, label: &mut String) {
+        match self.labels.get(english) {
+            Some(translated) => label.push_str(translated),
+            None => {
+                if let Some(translate) = &self.translate {
+                    translate(english, label);
+                }
+            }
+        }
+    }
+}
@@ -3,64 +3,1053 @@
 //! Add a diagnostics overlay (with an FPS counter) in Bevy.
 //!
 //! This crate provides a Bevy [plugin](ScreenDiagsPlugin) to add the diagnostics overlay.
+//!
+//! The diagnostics are split between the [core plugin](ScreenDiagsCorePlugin), which takes the
+//! readings without drawing anything, and the [UI plugin](ScreenDiagsUiPlugin), which draws them
+//! with Bevy UI. Servers and custom frontends can add the core plugin alone.
+
+mod auto_hide;
+mod benchmark;
+#[cfg(feature = "gpu-timings")]
+mod bottleneck;
+mod budget;
+#[cfg(feature = "config-file")]
+mod config_file;
+#[cfg(feature = "console")]
+mod console;
+mod custom;
+#[cfg(feature = "linux-device")]
+mod device;
+#[cfg(feature = "egui")]
+mod egui_overlay;
+mod gesture;
+#[cfg(feature = "gpu-timings")]
+mod gpu_timings;
+mod graph;
+mod histogram;
+mod history;
+mod labels;
+mod log_sink;
+mod overlay;
+#[cfg(feature = "rapier")]
+mod physics;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+mod recorder;
+mod screenshot;
+mod spikes;
+#[cfg(feature = "sysinfo")]
+mod system;
+mod terminal;
+mod thresholds;
+#[cfg(feature = "system-timings")]
+mod timings;
+#[cfg(target_arch = "wasm32")]
+mod web;
 
 use std::fmt::Write;
 
+#[cfg(feature = "audio")]
+use bevy::audio::AudioSinkPlayback;
+#[cfg(feature = "builtin-font")]
+use bevy::reflect::TypeUuid;
 use bevy::{
-    diagnostic::{FrameTimeDiagnosticsPlugin, DiagnosticsStore},
+    diagnostic::{
+        Diagnostic, DiagnosticId, DiagnosticsStore, EntityCountDiagnosticsPlugin,
+        FrameTimeDiagnosticsPlugin,
+    },
+    ecs::{archetype::Archetypes, component::Components, system::SystemParam},
     prelude::*,
-    utils::Duration,
+    render::{render_resource::PrimitiveTopology, renderer::RenderAdapterInfo},
+    ui::FocusPolicy,
+    utils::{Duration, HashMap},
+    window::{PrimaryWindow, WindowResized, WindowScaleFactorChanged},
+};
+#[cfg(feature = "renet")]
+use bevy_renet::renet::RenetClient;
+
+pub use auto_hide::ScreenDiagsAutoHide;
+pub use benchmark::{
+    BenchmarkReport, ScreenDiagsBaseline, ScreenDiagsBenchmark, ScreenDiagsBenchmarkLength,
 };
+pub use budget::ScreenDiagsBudgetBar;
+#[cfg(feature = "config-file")]
+pub use config_file::ScreenDiagsConfigFilePlugin;
+#[cfg(feature = "console")]
+pub use console::ScreenDiagsConsolePlugin;
+pub use custom::ScreenDiagsRows;
+#[cfg(feature = "egui")]
+pub use egui_overlay::ScreenDiagsEguiPlugin;
+pub use gesture::ScreenDiagsTouchGesture;
+pub use graph::ScreenDiagsGraph;
+pub use histogram::ScreenDiagsHistogram;
+use history::FixedUpdateHistory;
+pub use history::{ScreenDiagsFrameSample, ScreenDiagsHistory};
+pub use labels::ScreenDiagsLabels;
+pub use log_sink::ScreenDiagsLog;
+pub use overlay::{
+    despawn_overlay, spawn_overlay, ScreenDiagsCamera, ScreenDiagsEveryWindow, ScreenDiagsOverlay,
+    ScreenDiagsWorldSpace,
+};
+#[cfg(feature = "prometheus")]
+pub use prometheus::ScreenDiagsPrometheusPlugin;
+pub use recorder::{ScreenDiagsFlushRecording, ScreenDiagsRecordFormat, ScreenDiagsRecorder};
+pub use screenshot::{ScreenDiagsScreenshot, ScreenDiagsScreenshotOverlay};
+pub use spikes::{FrameSpikeEvent, ScreenDiagsSpikes};
+pub use terminal::{ScreenDiagsTerminal, ScreenDiagsTerminalStyle};
+pub use thresholds::{FpsBelowThreshold, FpsRecovered, ScreenDiagsFpsThreshold};
+#[cfg(feature = "system-timings")]
+pub use timings::{ScreenDiagsSystemTimings, ScreenDiagsTimingLayer};
 
+#[cfg_attr(feature = "builtin-font", allow(dead_code))]
+const FONT_PATH: &str = "fonts/screen-diags-font.ttf";
 const FONT_SIZE: f32 = 32.0;
 const FONT_COLOR: Color = Color::RED;
 const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+const HISTORY_WINDOW: Duration = Duration::from_secs(5);
 
-const STRING_FORMAT: &str = "FPS: ";
-const STRING_INITIAL: &str = "FPS: ...";
+const STRING_INITIAL: &str = "...";
 
-/// A plugin that draws diagnostics on-screen with Bevy UI.
-/// Currently only the FPS is displayed.
+/// A plugin that builds the diagnostics resources, and adds the
+/// [core plugin](ScreenDiagsCorePlugin) to take the readings.
+/// By default only the FPS is displayed; add rows to the [config](ScreenDiagsConfig) to show more.
 ///
 /// Use the [marker struct](ScreenDiagsText) to customise the FPS counter appearance,
-/// and the [resource](ScreenDiagsState) to control its behaviour.
-pub struct ScreenDiagsPlugin;
+/// and the [resource](ScreenDiagsState) or the [event](ScreenDiagsToggle) to control its behaviour.
+///
+/// The plugin is a builder for the resources, such as
+/// `ScreenDiagsPlugin::default().font_size(24.0).interval(Duration::from_millis(500))`.
+/// Settings made here replace the resources when the plugin is built, so add it before
+/// [ScreenDiagsUiPlugin].
+#[derive(Clone, Debug, Default)]
+pub struct ScreenDiagsPlugin {
+    config: Option<ScreenDiagsConfig>,
+    style: Option<ScreenDiagsStyle>,
+    start_hidden: bool,
+    log: Option<ScreenDiagsLog>,
+    terminal: Option<ScreenDiagsTerminal>,
+    debug_only: bool,
+}
+
+impl ScreenDiagsPlugin {
+    /// Uses this config.
+    pub fn config(mut self, config: ScreenDiagsConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Adds a row to the config.
+    pub fn with_row(mut self, row: ScreenDiagsRow) -> Self {
+        self.config_mut().rows.push(row);
+        self
+    }
+
+    /// Sets the time between updates of the display.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.config_mut().interval = interval;
+        self
+    }
+
+    /// Sets the length of the window that the statistics are taken over.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.config_mut().window = window;
+        self
+    }
+
+    /// Leaves the frames out of the statistics while the window is unfocused or minimized.
+    pub fn pause_when_unfocused(mut self) -> Self {
+        self.config_mut().pause_when_unfocused = true;
+        self
+    }
+
+    /// Sets how the FPS and frame time rows are smoothed.
+    pub fn smoothing(mut self, smoothing: ScreenDiagsSmoothing) -> Self {
+        self.config_mut().smoothing = smoothing;
+        self
+    }
+
+    /// Sets whether the FPS and frame time rows show the smoothed value, the last, or both.
+    pub fn fps_display(mut self, fps_display: ScreenDiagsFpsDisplay) -> Self {
+        self.config_mut().fps_display = fps_display;
+        self
+    }
+
+    /// Uses this style.
+    pub fn style(mut self, style: ScreenDiagsStyle) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    /// Sets the font.
+    pub fn font(mut self, font: impl Into<ScreenDiagsFont>) -> Self {
+        self.style_mut().font = font.into();
+        self
+    }
+
+    /// Sets the font size.
+    pub fn font_size(mut self, font_size: f32) -> Self {
+        self.style_mut().font_size = font_size;
+        self
+    }
+
+    /// Sets the text colour.
+    pub fn color(mut self, color: Color) -> Self {
+        self.style_mut().color = color;
+        self
+    }
+
+    /// Sets where the diagnostics are placed on the screen.
+    pub fn position(mut self, position: ScreenDiagsPosition) -> Self {
+        self.style_mut().position = position;
+        self
+    }
+
+    /// Sets the text colour and panel to those of the theme.
+    pub fn theme(mut self, theme: ScreenDiagsTheme) -> Self {
+        theme.apply(self.style_mut());
+        self
+    }
+
+    /// Sets the stacking order of the overlay among the other UI.
+    pub fn z_index(mut self, z_index: ZIndex) -> Self {
+        self.style_mut().z_index = z_index;
+        self
+    }
+
+    /// Starts with the display disabled, until it is [enabled](ScreenDiagsState::enable).
+    pub fn start_hidden(mut self) -> Self {
+        self.start_hidden = true;
+        self
+    }
+
+    /// Also logs the rows once per interval.
+    pub fn log_every(mut self, interval: Duration) -> Self {
+        self.log = Some(ScreenDiagsLog::new(interval));
+        self
+    }
+
+    /// Also prints the rows to the terminal at each update, for servers with no window.
+    pub fn print_to_terminal(mut self, style: ScreenDiagsTerminalStyle) -> Self {
+        let mut terminal = ScreenDiagsTerminal::default();
+        terminal.style = style;
+        self.terminal = Some(terminal);
+        self
+    }
+
+    /// Makes this plugin and the frontends added after it do nothing in release builds, so that
+    /// they can stay in the app for good. Not even the resources and events are added, so the
+    /// app's own systems that use them must take them as `Option`s. The `release-disable` feature
+    /// does the same for every app.
+    pub fn debug_only(mut self) -> Self {
+        self.debug_only = true;
+        self
+    }
+
+    fn config_mut(&mut self) -> &mut ScreenDiagsConfig {
+        self.config.get_or_insert_with(ScreenDiagsConfig::default)
+    }
+
+    fn style_mut(&mut self) -> &mut ScreenDiagsStyle {
+        self.style.get_or_insert_with(ScreenDiagsStyle::default)
+    }
+}
 
 impl Plugin for ScreenDiagsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(FrameTimeDiagnosticsPlugin::default())
-            .add_systems(Update, update_frame_counter)
+        if self.debug_only && !cfg!(debug_assertions) {
+            app.insert_resource(Disabled);
+        }
+        if is_disabled(app) {
+            return;
+        }
+        if let Some(config) = &self.config {
+            app.insert_resource(config.clone());
+        }
+        if let Some(style) = &self.style {
+            app.insert_resource(style.clone());
+        }
+        if let Some(log) = &self.log {
+            app.insert_resource(log.clone());
+        }
+        if let Some(terminal) = &self.terminal {
+            app.insert_resource(terminal.clone());
+        }
+        if self.start_hidden {
+            app.world
+                .get_resource_or_insert_with(ScreenDiagsState::default)
+                .disable();
+        }
+        if !app.is_plugin_added::<ScreenDiagsCorePlugin>() {
+            app.add_plugins(ScreenDiagsCorePlugin);
+        }
+    }
+}
+
+/// A plugin that samples the diagnostics and keeps their history, sends the diagnostics events and
+/// handles the toggles, without drawing anything. Add a frontend such as [ScreenDiagsUiPlugin] to
+/// show the readings, or read them from [ScreenDiagsReadings] yourself. A dedicated server can add
+/// this plugin alone, with the [log](ScreenDiagsLog) or the [terminal](ScreenDiagsTerminal) output.
+///
+/// Insert the config before adding the plugin, so that the diagnostics plugins the rows need are
+/// added too. For the rows of other [overlays](ScreenDiagsOverlay), add them to the app yourself.
+pub struct ScreenDiagsCorePlugin;
+
+impl Plugin for ScreenDiagsCorePlugin {
+    fn build(&self, app: &mut App) {
+        if is_disabled(app) {
+            return;
+        }
+
+        let shows_entity_count = app
+            .world
+            .get_resource_or_insert_with(ScreenDiagsConfig::default)
+            .shows(ScreenDiagsMetric::EntityCount);
+
+        app.add_event::<ScreenDiagsToggle>()
+            .add_event::<ScreenDiagsVisibilityChanged>()
+            .add_event::<ScreenDiagsFlushRecording>()
+            .add_event::<FrameSpikeEvent>()
+            .add_event::<BenchmarkReport>()
+            .add_event::<FpsBelowThreshold>()
+            .add_event::<FpsRecovered>()
             .init_resource::<ScreenDiagsState>()
+            .init_resource::<ScreenDiagsDisplayMode>()
+            .init_resource::<ScreenDiagsRows>()
+            .init_resource::<ScreenDiagsRowsConfig>()
+            .init_resource::<ScreenDiagsReadings>()
+            .init_resource::<ScreenDiagsHistory>()
+            .init_resource::<FixedUpdateHistory>()
+            .init_resource::<ScreenDiagsSpikes>()
+            .init_resource::<ScreenDiagsFrames>()
+            .init_resource::<ScreenDiagsTrackedEntity>()
+            .init_resource::<ScreenDiagsScale>()
             .init_resource::<FrameCounter>();
+
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        if shows_entity_count && !app.is_plugin_added::<EntityCountDiagnosticsPlugin>() {
+            app.add_plugins(EntityCountDiagnosticsPlugin);
+        }
+
+        app.configure_sets(
+            Update,
+            (
+                ScreenDiagsSet::Tick,
+                ScreenDiagsSet::Sample,
+                ScreenDiagsSet::Display,
+            )
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            (
+                (
+                    toggle_on_hotkey.run_if(resource_exists::<ScreenDiagsHotkey>()),
+                    gesture::toggle_on_gesture.run_if(resource_exists::<ScreenDiagsTouchGesture>()),
+                    cycle_mode_on_hotkey.run_if(resource_exists::<ScreenDiagsModeHotkey>()),
+                    handle_toggle_events,
+                    auto_hide::tick_auto_hide.run_if(resource_exists::<ScreenDiagsAutoHide>()),
+                    update_on_resize,
+                    count_frames,
+                    spikes::detect_spikes,
+                    history::record_frame_time,
+                    benchmark::capture_benchmark.run_if(resource_exists::<ScreenDiagsBenchmark>()),
+                    tick_timer,
+                    log_sink::tick_log.run_if(resource_exists::<ScreenDiagsLog>()),
+                    recorder::record_frames.run_if(resource_exists::<ScreenDiagsRecorder>()),
+                )
+                    .chain()
+                    .in_set(ScreenDiagsSet::Tick),
+                (
+                    custom::sample_custom_rows.run_if(custom::custom_rows_due),
+                    update_frame_counter,
+                    thresholds::check_fps_threshold
+                        .run_if(resource_exists::<ScreenDiagsFpsThreshold>()),
+                )
+                    .chain()
+                    .in_set(ScreenDiagsSet::Sample),
+                (
+                    notify_visibility_changed,
+                    log_sink::write_log.run_if(resource_exists::<ScreenDiagsLog>()),
+                    terminal::print_to_terminal.run_if(resource_exists::<ScreenDiagsTerminal>()),
+                )
+                    .in_set(ScreenDiagsSet::Display),
+            ),
+        )
+        .add_systems(FixedUpdate, history::record_fixed_update)
+        .add_systems(PostUpdate, clear_update_now)
+        .add_systems(
+            Last,
+            recorder::flush_recording.run_if(resource_exists::<ScreenDiagsRecorder>()),
+        )
+        .register_type::<ScreenDiagsConfig>()
+        .register_type::<ScreenDiagsRow>()
+        .register_type::<ScreenDiagsRowsConfig>()
+        .register_type::<ScreenDiagsSmoothing>()
+        .register_type::<ScreenDiagsFpsDisplay>()
+        .register_type::<ScreenDiagsMetric>()
+        .register_type::<ScreenDiagsState>()
+        .register_type::<ScreenDiagsDisplayMode>()
+        .register_type::<ScreenDiagsHotkey>()
+        .register_type::<ScreenDiagsModeHotkey>()
+        .register_type::<ScreenDiagsTouchGesture>()
+        .register_type::<ScreenDiagsAutoHide>()
+        .register_type::<ScreenDiagsSpikes>()
+        .register_type::<ScreenDiagsFpsThreshold>()
+        .register_type::<ScreenDiagsFrames>()
+        .register_type::<ScreenDiagsTrackedEntity>()
+        .register_type::<ScreenDiagsScale>()
+        .register_type::<ScreenDiagsTracked>();
+
+        #[cfg(feature = "sysinfo")]
+        {
+            system::register(app);
+            app.add_systems(
+                Update,
+                system::sample_system_info
+                    .in_set(ScreenDiagsSet::Sample)
+                    .before(update_frame_counter),
+            );
+        }
+
+        #[cfg(feature = "rapier")]
+        physics::register(app);
+
+        #[cfg(target_arch = "wasm32")]
+        app.add_systems(Startup, web::listen_for_visibility);
+
+        #[cfg(feature = "linux-device")]
+        {
+            device::register(app);
+            app.add_systems(
+                Update,
+                device::sample_device_info
+                    .in_set(ScreenDiagsSet::Sample)
+                    .before(update_frame_counter),
+            );
+        }
+
+        #[cfg(feature = "gpu-timings")]
+        {
+            gpu_timings::register(app);
+            bottleneck::register(app);
+        }
+
+        #[cfg(feature = "system-timings")]
+        app.add_systems(
+            Update,
+            timings::collect_system_timings
+                .run_if(resource_exists::<ScreenDiagsSystemTimings>())
+                .in_set(ScreenDiagsSet::Sample)
+                .before(update_frame_counter),
+        );
+    }
+
+    #[cfg(feature = "gpu-timings")]
+    fn finish(&self, app: &mut App) {
+        if !is_disabled(app) {
+            gpu_timings::finish(app);
+        }
     }
 }
 
-/// A plugin to write the FPS counter to the screen
+/// A plugin to write the FPS counter to the screen with Bevy UI, which adds the
+/// [core plugin](ScreenDiagsCorePlugin) if it is missing.
 ///
-/// Use the [marker struct](ScreenDiagsText) to customise the FPS counter appearance,
-/// and the [resource](ScreenDiagsState) to control its behaviour.
-pub struct ScreenDiagsTextPlugin;
+/// Use the [style resource](ScreenDiagsStyle) or the [marker struct](ScreenDiagsText) to customise
+/// the FPS counter appearance, and the [resource](ScreenDiagsState) to control its behaviour.
+/// To show more than one set of diagnostics, spawn more [overlays](ScreenDiagsOverlay).
+pub struct ScreenDiagsUiPlugin;
+
+/// The UI plugin, by the name it had before the [core plugin](ScreenDiagsCorePlugin) was split
+/// out.
+pub use ScreenDiagsUiPlugin as ScreenDiagsTextPlugin;
+
+impl Plugin for ScreenDiagsUiPlugin {
+    fn build(&self, app: &mut App) {
+        if !is_disabled(app) {
+            add_overlays(app);
+            app.add_systems(Startup, overlay::spawn_primary_overlay);
+        }
+    }
+}
 
-impl Plugin for ScreenDiagsTextPlugin {
+impl ScreenDiagsPlugin {
+    /// A plugin that writes the diagnostics to the screen like [ScreenDiagsUiPlugin], but only
+    /// while the app is in this state. The overlay is spawned on first entering the state, and
+    /// hidden on leaving it.
+    pub fn run_in_state<S: States>(state: S) -> ScreenDiagsStatePlugin<S> {
+        ScreenDiagsStatePlugin { state }
+    }
+}
+
+/// A plugin to write the FPS counter to the screen in one state of the app.
+///
+/// Make this with [ScreenDiagsPlugin::run_in_state], and add it instead of [ScreenDiagsUiPlugin].
+pub struct ScreenDiagsStatePlugin<S: States> {
+    state: S,
+}
+
+impl<S: States> Plugin for ScreenDiagsStatePlugin<S> {
     fn build(&self, app: &mut App) {
-        app.add_plugins(ScreenDiagsPlugin)
-            .add_systems(Startup, spawn_text)
-            .add_systems(Update, update_text);
+        if is_disabled(app) {
+            return;
+        }
+        add_overlays(app);
+        app.add_systems(OnEnter(self.state.clone()), overlay::show_primary_overlay)
+            .add_systems(OnExit(self.state.clone()), overlay::hide_primary_overlay);
+    }
+}
+
+// Marks an app whose diagnostics plugins do nothing, as a release build of a debug-only plugin
+#[derive(Resource)]
+struct Disabled;
+
+// Whether the plugins should do nothing in this build
+pub(crate) fn is_disabled(app: &App) -> bool {
+    cfg!(all(feature = "release-disable", not(debug_assertions)))
+        || app.world.contains_resource::<Disabled>()
+}
+
+// Adds everything needed to draw overlays, apart from spawning the primary one
+fn add_overlays(app: &mut App) {
+    if !app.is_plugin_added::<ScreenDiagsCorePlugin>() {
+        app.add_plugins(ScreenDiagsCorePlugin);
+    }
+    app.init_resource::<ScreenDiagsStyle>()
+        .register_type::<ScreenDiagsStyle>()
+        .register_type::<ScreenDiagsFont>()
+        .register_type::<ScreenDiagsPosition>()
+        .register_type::<ScreenDiagsPanel>()
+        .register_type::<ScreenDiagsTheme>()
+        .register_type::<ScreenDiagsShadow>()
+        .register_type::<ScreenDiagsFpsWarning>()
+        .register_type::<ScreenDiagsOverlay>()
+        .register_type::<ScreenDiagsWorldSpace>()
+        .register_type::<ScreenDiagsCamera>()
+        .register_type::<ScreenDiagsEveryWindow>()
+        .register_type::<ScreenDiagsGraph>()
+        .register_type::<ScreenDiagsHistogram>()
+        .register_type::<ScreenDiagsBudgetBar>()
+        .register_type::<ScreenDiagsText>()
+        .add_systems(
+            Update,
+            (
+                (
+                    overlay::spawn_window_overlays
+                        .run_if(resource_exists::<ScreenDiagsEveryWindow>()),
+                    overlay::sync_primary_overlay,
+                    overlay::rebuild_on_ui_scale,
+                    overlay::rebuild_on_scale,
+                    overlay::rebuild_on_mode,
+                    apply_deferred,
+                    overlay::build_overlays,
+                    apply_deferred,
+                    overlay::check_overlay_fonts,
+                    overlay::tick_overlays,
+                )
+                    .chain()
+                    .in_set(ScreenDiagsSet::Tick)
+                    .after(tick_timer),
+                (
+                    overlay::place_overlays,
+                    overlay::place_window_overlays,
+                    overlay::drag_overlays.run_if(
+                        resource_exists::<Input<MouseButton>>().and_then(overlay::any_draggable),
+                    ),
+                    overlay::update_overlay_visibility,
+                    screenshot::take_screenshot.run_if(resource_exists::<ScreenDiagsScreenshot>()),
+                    overlay::update_overlays,
+                    graph::update_graph,
+                    histogram::update_histogram,
+                    budget::update_budget_bar,
+                )
+                    .chain()
+                    .in_set(ScreenDiagsSet::Display),
+            ),
+        );
+
+    #[cfg(feature = "builtin-font")]
+    app.add_systems(Startup, load_builtin_font);
+}
+
+/// The stages of the diagnostics update in the `Update` schedule, which run in this order.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScreenDiagsSet {
+    /// Handling the toggles and ticking the timers.
+    Tick,
+    /// Taking the readings.
+    Sample,
+    /// Updating the display.
+    Display,
+}
+
+#[cfg(feature = "builtin-font")]
+const BUILTIN_FONT_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Font::TYPE_UUID, 0x5d1a_6f0c_3b27_49e8);
+
+// Registers the embedded font with the font assets
+#[cfg(feature = "builtin-font")]
+fn load_builtin_font(mut fonts: ResMut<Assets<Font>>) {
+    let font =
+        Font::try_from_bytes(include_bytes!("../assets/fonts/screen-diags-font.ttf").to_vec())
+            .expect("the embedded font is valid");
+    fonts.set_untracked(BUILTIN_FONT_HANDLE, font);
+}
+
+/// The font used for the FPS text.
+#[derive(Reflect, Clone, Debug, PartialEq)]
+pub enum ScreenDiagsFont {
+    /// Load the font from this asset path.
+    Path(String),
+    /// Use a font that the app has already loaded, such as the font of its own UI.
+    Handle(Handle<Font>),
+    /// Use the font embedded in the crate, which needs no asset files.
+    #[cfg(feature = "builtin-font")]
+    Builtin,
+    /// Use Bevy's own default font, which is embedded with Bevy's `default_font` feature.
+    Bevy,
+}
+
+impl Default for ScreenDiagsFont {
+    #[cfg(feature = "builtin-font")]
+    fn default() -> Self {
+        Self::Builtin
+    }
+
+    #[cfg(not(feature = "builtin-font"))]
+    fn default() -> Self {
+        Self::Path(FONT_PATH.to_string())
+    }
+}
+
+impl From<&str> for ScreenDiagsFont {
+    fn from(path: &str) -> Self {
+        Self::Path(path.to_string())
+    }
+}
+
+impl From<String> for ScreenDiagsFont {
+    fn from(path: String) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl From<Handle<Font>> for ScreenDiagsFont {
+    fn from(handle: Handle<Font>) -> Self {
+        Self::Handle(handle)
+    }
+}
+
+impl ScreenDiagsFont {
+    fn handle(&self, asset_server: &AssetServer) -> Handle<Font> {
+        match self {
+            Self::Path(path) => asset_server.load(path.as_str()),
+            Self::Handle(handle) => handle.clone(),
+            #[cfg(feature = "builtin-font")]
+            Self::Builtin => BUILTIN_FONT_HANDLE.typed(),
+            Self::Bevy => Handle::default(),
+        }
+    }
+
+    // The font used when the chosen one fails to load, which needs no asset files
+    #[cfg(feature = "builtin-font")]
+    fn fallback() -> Self {
+        Self::Builtin
+    }
+
+    #[cfg(not(feature = "builtin-font"))]
+    fn fallback() -> Self {
+        Self::Bevy
+    }
+}
+
+/// The style resource, read when the FPS text is spawned.
+///
+/// Insert this resource before the app starts to change the appearance of the FPS counter.
+/// Changing it later rebuilds the FPS counter with the new style.
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct ScreenDiagsStyle {
+    /// The font.
+    pub font: ScreenDiagsFont,
+    /// The font used instead if [font](ScreenDiagsStyle::font) fails to load, with a warning.
+    /// By default the embedded font with the `builtin-font` feature, or else Bevy's default font.
+    pub fallback_font: ScreenDiagsFont,
+    /// The font size.
+    pub font_size: f32,
+    /// The text colour.
+    pub color: Color,
+    /// Where the diagnostics are placed on the screen.
+    pub position: ScreenDiagsPosition,
+    /// The gap between the diagnostics and the edges of the screen, for the corner positions.
+    pub margin: Val,
+    /// The background panel behind the diagnostics, such as `Some(ScreenDiagsPanel::default())`,
+    /// or `None` for no panel. None by default.
+    pub panel: Option<ScreenDiagsPanel>,
+    /// The colours of the FPS rows as pairs of lowest FPS and colour, such as
+    /// `vec![(60.0, Color::GREEN), (30.0, Color::YELLOW)]`. Each update, the row takes the colour
+    /// of the first pair whose FPS it reaches, or the text colour if none. Empty by default.
+    pub fps_colors: Vec<(f64, Color)>,
+    /// The colours of the [target FPS](ScreenDiagsRow::fps_target) rows, as pairs of the lowest
+    /// fraction of the target and colour. Each update, the row takes the colour of the first pair
+    /// whose fraction of the target its FPS reaches, or the text colour if none. By default, green
+    /// at the target, yellow within 10% of it and red below.
+    pub target_colors: Vec<(f64, Color)>,
+    /// A warning that makes the FPS row blink or pulse while the FPS is low, or `None` for none.
+    pub fps_warning: Option<ScreenDiagsFpsWarning>,
+    /// The stacking order of the overlay among the other UI. Set `ZIndex::Global(i32::MAX)` to
+    /// draw it above all other UI, such as full-screen menus.
+    pub z_index: ZIndex,
+    /// Whether the overlay keeps the same size whatever the `UiScale`, so that it does not grow
+    /// with a scaled-up game UI. This applies to the font size, margin and panel padding.
+    pub ignore_ui_scale: bool,
+    /// Whether several rows are laid out in two columns, of labels and of values, so that the
+    /// values line up. This does not apply to a single row, in the
+    /// [compact](ScreenDiagsDisplayMode::Compact) mode, or to overlays in the world.
+    pub columns: bool,
+    /// A shadow or outline drawn behind the text, to keep it readable over both bright and dark
+    /// scenes, or `None` for none.
+    pub shadow: Option<ScreenDiagsShadow>,
+    /// How long the overlay takes to fade in when it is shown, and out when it is hidden. Zero by
+    /// default, for no fade.
+    pub fade: Duration,
+    /// Whether the overlay can be dragged around the window with the left mouse button, by the
+    /// border of its panel, or anywhere on it if the panel has no padding. The position it is
+    /// dropped at is written back into the style as an
+    /// [absolute](ScreenDiagsPosition::Absolute) position.
+    pub draggable: bool,
+    /// Whether the overlay stops clicks from reaching the UI under it. By default it lets them
+    /// through, apart from on the border it is [dragged](ScreenDiagsStyle::draggable) by.
+    pub focus_policy: FocusPolicy,
+}
+
+impl ScreenDiagsStyle {
+    // The colour the row takes from its reading at this time since startup, if it has one
+    fn row_color(
+        &self,
+        metric: ScreenDiagsMetric,
+        readings: &ScreenDiagsReadings,
+        elapsed: f64,
+    ) -> Option<Color> {
+        if let ScreenDiagsMetric::FpsTarget(target) = metric {
+            let fps = readings.get(metric)?;
+            return self
+                .target_colors
+                .iter()
+                .find(|(fraction, _)| fps >= f64::from(target) * fraction)
+                .map(|(_, color)| *color);
+        }
+        if metric != ScreenDiagsMetric::Fps {
+            return None;
+        }
+        let fps = readings.get(metric)?;
+        if let Some(warning) = &self.fps_warning {
+            if fps < warning.below {
+                let normal = self.fps_color(fps);
+                return Some(warning.color_at(normal, elapsed));
+            }
+        }
+        if self.fps_colors.is_empty() {
+            return None;
+        }
+        Some(self.fps_color(fps))
+    }
+
+    // Whether the FPS row is blinking, so that it needs redrawing every frame
+    fn is_warning(&self, readings: &ScreenDiagsReadings) -> bool {
+        self.fps_warning.as_ref().is_some_and(|warning| {
+            readings
+                .get(ScreenDiagsMetric::Fps)
+                .is_some_and(|fps| fps < warning.below)
+        })
+    }
+
+    // The colour of the FPS row for this FPS, without any warning
+    fn fps_color(&self, fps: f64) -> Color {
+        self.fps_colors
+            .iter()
+            .find(|(lowest, _)| fps >= *lowest)
+            .map_or(self.color, |(_, color)| *color)
+    }
+}
+
+impl Default for ScreenDiagsStyle {
+    fn default() -> Self {
+        Self {
+            font: ScreenDiagsFont::default(),
+            fallback_font: ScreenDiagsFont::fallback(),
+            font_size: FONT_SIZE,
+            color: FONT_COLOR,
+            position: ScreenDiagsPosition::default(),
+            margin: Val::Px(0.0),
+            panel: None,
+            fps_colors: Vec::new(),
+            target_colors: vec![(1.0, Color::GREEN), (0.9, Color::YELLOW), (0.0, Color::RED)],
+            fps_warning: None,
+            z_index: ZIndex::default(),
+            ignore_ui_scale: false,
+            columns: true,
+            shadow: None,
+            fade: Duration::ZERO,
+            draggable: false,
+            focus_policy: FocusPolicy::Pass,
+        }
+    }
+}
+
+/// A warning that makes the FPS row blink or pulse in another colour while the FPS is below a
+/// threshold, returning to normal when it recovers.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub struct ScreenDiagsFpsWarning {
+    /// The FPS below which the row blinks.
+    pub below: f64,
+    /// The colour the row blinks in.
+    pub color: Color,
+    /// The time for the row to go from its usual colour to the warning colour and back.
+    pub period: Duration,
+    /// Whether the colour changes smoothly, rather than switching.
+    pub pulse: bool,
+}
+
+impl ScreenDiagsFpsWarning {
+    /// A warning that blinks red twice a second below this FPS.
+    pub fn below(fps: f64) -> Self {
+        Self {
+            below: fps,
+            color: Color::RED,
+            period: Duration::from_millis(500),
+            pulse: false,
+        }
+    }
+
+    // The colour at this time since startup, between the usual colour and the warning colour
+    fn color_at(&self, normal: Color, elapsed: f64) -> Color {
+        let period = self.period.as_secs_f64();
+        if period <= 0.0 {
+            return self.color;
+        }
+        let phase = (elapsed / period).fract();
+        if !self.pulse {
+            return if phase < 0.5 { self.color } else { normal };
+        }
+        // Starts at the warning colour and fades to the usual one halfway through
+        let weight = (0.5 + 0.5 * (phase * std::f64::consts::TAU).cos()) as f32;
+        let [red, green, blue, alpha] = normal.as_rgba_f32();
+        let [warning_red, warning_green, warning_blue, warning_alpha] = self.color.as_rgba_f32();
+        Color::rgba(
+            red + (warning_red - red) * weight,
+            green + (warning_green - green) * weight,
+            blue + (warning_blue - blue) * weight,
+            alpha + (warning_alpha - alpha) * weight,
+        )
+    }
+}
+
+/// The background panel behind the diagnostics.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub struct ScreenDiagsPanel {
+    /// The panel colour. Use the alpha to set its opacity.
+    pub color: Color,
+    /// The gap between the edges of the panel and the diagnostics.
+    pub padding: UiRect,
+}
+
+impl Default for ScreenDiagsPanel {
+    fn default() -> Self {
+        Self {
+            color: Color::rgba(0.0, 0.0, 0.0, 0.5),
+            padding: UiRect::all(Val::Px(4.0)),
+        }
+    }
+}
+
+/// A shadow or outline drawn behind the text, as copies of it in another colour.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub enum ScreenDiagsShadow {
+    /// One copy of the text, moved by this offset in pixels, right and down.
+    Drop {
+        /// The colour of the shadow.
+        color: Color,
+        /// How far the shadow is moved from the text.
+        offset: Vec2,
+    },
+    /// Copies of the text moved in eight directions, which outline it.
+    Outline {
+        /// The colour of the outline.
+        color: Color,
+        /// The width of the outline, in pixels.
+        width: f32,
+    },
+}
+
+impl ScreenDiagsShadow {
+    /// A black shadow, one pixel right and down from the text.
+    pub fn drop() -> Self {
+        Self::Drop {
+            color: Color::BLACK,
+            offset: Vec2::ONE,
+        }
+    }
+
+    /// A black outline, one pixel wide.
+    pub fn outline() -> Self {
+        Self::Outline {
+            color: Color::BLACK,
+            width: 1.0,
+        }
+    }
+
+    // The colour of the copies
+    fn color(self) -> Color {
+        match self {
+            Self::Drop { color, .. } | Self::Outline { color, .. } => color,
+        }
+    }
+
+    // The offsets of the copies from the text
+    fn offsets(self) -> Vec<Vec2> {
+        match self {
+            Self::Drop { offset, .. } => vec![offset],
+            Self::Outline { width, .. } => [
+                Vec2::new(-1.0, -1.0),
+                Vec2::new(0.0, -1.0),
+                Vec2::new(1.0, -1.0),
+                Vec2::new(-1.0, 0.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(-1.0, 1.0),
+                Vec2::new(0.0, 1.0),
+                Vec2::new(1.0, 1.0),
+            ]
+            .into_iter()
+            .map(|direction| direction * width)
+            .collect(),
+        }
+    }
+}
+
+/// A preset of the text colour and panel of the [style](ScreenDiagsStyle).
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScreenDiagsTheme {
+    /// White text on a dark panel.
+    Dark,
+    /// Dark text on a light panel.
+    Light,
+    /// Yellow text on an opaque black panel, with more padding.
+    HighContrast,
+    /// White text with no panel.
+    Minimal,
+}
+
+impl ScreenDiagsTheme {
+    /// Sets the text colour and panel of the style to those of the theme, leaving the rest.
+    pub fn apply(self, style: &mut ScreenDiagsStyle) {
+        let (color, panel) = match self {
+            Self::Dark => (
+                Color::WHITE,
+                Some(ScreenDiagsPanel {
+                    color: Color::rgba(0.05, 0.05, 0.05, 0.7),
+                    padding: UiRect::all(Val::Px(6.0)),
+                }),
+            ),
+            Self::Light => (
+                Color::rgb(0.1, 0.1, 0.1),
+                Some(ScreenDiagsPanel {
+                    color: Color::rgba(0.95, 0.95, 0.95, 0.8),
+                    padding: UiRect::all(Val::Px(6.0)),
+                }),
+            ),
+            Self::HighContrast => (
+                Color::YELLOW,
+                Some(ScreenDiagsPanel {
+                    color: Color::BLACK,
+                    padding: UiRect::all(Val::Px(8.0)),
+                }),
+            ),
+            Self::Minimal => (Color::WHITE, None),
+        };
+        style.color = color;
+        style.panel = panel;
+    }
+}
+
+impl From<ScreenDiagsTheme> for ScreenDiagsStyle {
+    fn from(theme: ScreenDiagsTheme) -> Self {
+        let mut style = Self::default();
+        theme.apply(&mut style);
+        style
+    }
+}
+
+/// Where the diagnostics are placed on the screen.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq)]
+pub enum ScreenDiagsPosition {
+    /// In the top left corner.
+    #[default]
+    TopLeft,
+    /// In the top right corner.
+    TopRight,
+    /// In the bottom left corner.
+    BottomLeft,
+    /// In the bottom right corner.
+    BottomRight,
+    /// At these offsets from the edges of the screen.
+    Absolute(UiRect),
+    /// In the layout of its parent node, for an overlay that is a child of the app's own UI,
+    /// such as a debug panel. See [spawn_overlay].
+    Inline,
+}
+
+impl ScreenDiagsPosition {
+    // Sets the node style to put the node in this position
+    fn apply(self, margin: Val, style: &mut Style) {
+        style.position_type = match self {
+            Self::Inline => PositionType::Relative,
+            _ => PositionType::Absolute,
+        };
+        let (top, right, bottom, left) = self.offsets(margin);
+        style.top = top;
+        style.right = right;
+        style.bottom = bottom;
+        style.left = left;
+    }
+
+    // The offsets from the top, right, bottom and left edges of the screen
+    fn offsets(self, margin: Val) -> (Val, Val, Val, Val) {
+        match self {
+            Self::TopLeft => (margin, Val::Auto, Val::Auto, margin),
+            Self::TopRight => (margin, margin, Val::Auto, Val::Auto),
+            Self::BottomLeft => (Val::Auto, Val::Auto, margin, margin),
+            Self::BottomRight => (Val::Auto, margin, margin, Val::Auto),
+            Self::Absolute(rect) => (rect.top, rect.right, rect.bottom, rect.left),
+            Self::Inline => (Val::Auto, Val::Auto, Val::Auto, Val::Auto),
+        }
     }
 }
 
 /// The diagnostics state resource.
 ///
 /// To disable the FPS counter, get a [ResMut](bevy::prelude::ResMut) reference to this struct and
-/// pause the timer. Unpause the timer to re-enable the counter.
-#[derive(Resource)]
+/// call [disable](ScreenDiagsState::disable), [enable](ScreenDiagsState::enable) or
+/// [toggle](ScreenDiagsState::toggle).
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
 pub struct ScreenDiagsState {
     /// The timer that triggers a diagnostics reading.
     /// Public, to allow flexible use, but in general use the methods to interact.
+    ///
+    /// Its duration is set from the [config](ScreenDiagsConfig::interval) whenever that changes.
+    ///
+    /// Pausing the timer to disable the counter is deprecated: it still works, but use
+    /// [disable](ScreenDiagsState::disable) instead.
     pub timer: Timer,
     /// A flag to indicate to update the display, even if the timer has not popped.
     /// Public, to allow flexible use, but in general use the methods to interact.
     pub update_now: bool,
+    enabled: bool,
+    // Whether the timer popped or an update was requested this frame
+    due: bool,
 }
 
 impl Default for ScreenDiagsState {
@@ -68,6 +1057,8 @@ impl Default for ScreenDiagsState {
         Self {
             timer: Timer::new(UPDATE_INTERVAL, TimerMode::Repeating),
             update_now: true,
+            enabled: true,
+            due: false,
         }
     }
 }
@@ -75,103 +1066,1694 @@ impl Default for ScreenDiagsState {
 impl ScreenDiagsState {
     /// Enable the FPS display.
     pub fn enable(&mut self) {
+        self.enabled = true;
         self.timer.unpause();
         self.update_now = true;
     }
 
     /// Disable the FPS display.
     pub fn disable(&mut self) {
-        self.timer.pause();
+        self.enabled = false;
         self.update_now = true;
     }
 
+    /// Enable the FPS display if it is disabled, and disable it if it is enabled.
+    pub fn toggle(&mut self) {
+        if self.enabled() {
+            self.disable();
+        } else {
+            self.enable();
+        }
+    }
+
     /// Is the FPS display enabled.
     pub fn enabled(&self) -> bool {
-        !self.timer.paused()
+        self.enabled && !self.timer.paused()
     }
 }
 
-/// Resource to get the current FPS.
-#[derive(Resource, Default)]
-pub struct FrameCounter(pub f64);
+/// The overall scale of the overlays, which multiplies their font sizes, margins and paddings, on
+/// top of the `UiScale`. It must be above zero.
+///
+/// Unlike the `UiScale`, it leaves the rest of the UI alone, so the overlay can be made readable
+/// when recording at 4K. The overlays are built again when it changes.
+#[derive(Resource, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Resource)]
+pub struct ScreenDiagsScale(pub f32);
 
-// Updates the frame_counter
-fn update_frame_counter(
-    time: Res<Time>,
-    diagnostics: Res<DiagnosticsStore>,
-    state_resource: Option<ResMut<ScreenDiagsState>>,
-    mut frame_counter: ResMut<FrameCounter>,
-) {
-    if let Some(mut state) = state_resource {
-        if state.update_now || state.timer.tick(time.delta()).just_finished() {
-            if state.timer.paused() {
-                return;
-            } else {
-                let fps_diags = extract_fps(&diagnostics);
+impl Default for ScreenDiagsScale {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
 
-                if let Some(fps) = fps_diags {
-                    frame_counter.0 = fps;
-                } else {
-                    frame_counter.0 = 0.0;
-                }
-            }
+/// The display mode resource, saying how much the overlays show.
+#[derive(Resource, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Resource)]
+pub enum ScreenDiagsDisplayMode {
+    /// The overlays are hidden.
+    Off,
+    /// Only the FPS is shown, with the FPS row of the config if it has one.
+    FpsOnly,
+    /// All the rows are shown on a single line.
+    Compact,
+    /// All the rows are shown, one per line.
+    #[default]
+    Verbose,
+}
+
+// The row shown in FPS-only mode when the config has no FPS row
+static FPS_ROW: ScreenDiagsRow = ScreenDiagsRow::new(ScreenDiagsMetric::Fps, 0);
+
+impl ScreenDiagsDisplayMode {
+    // The rows shown in this mode
+    fn rows(self, rows: &[ScreenDiagsRow]) -> &[ScreenDiagsRow] {
+        match self {
+            Self::Off => &[],
+            Self::FpsOnly => match rows
+                .iter()
+                .position(|row| row.metric == ScreenDiagsMetric::Fps)
+            {
+                Some(index) => &rows[index..=index],
+                None => std::slice::from_ref(&FPS_ROW),
+            },
+            Self::Compact | Self::Verbose => rows,
+        }
+    }
+
+    // The text between rows
+    fn separator(self) -> &'static str {
+        match self {
+            Self::Compact => " | ",
+            _ => "\n",
         }
     }
 }
 
-/// The marker on the text to be updated.
-#[derive(Component)]
-pub struct ScreenDiagsText;
+/// A value that the diagnostics display can show.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+// Diagnostic ids can't be reflected, so the metric is reflected as a whole
+#[reflect_value(Debug, PartialEq, Hash)]
+pub enum ScreenDiagsMetric {
+    /// The frames per second.
+    Fps,
+    /// The frames per second against this target, and how far short of or over it they are.
+    FpsTarget(u32),
+    /// The time taken per frame, in milliseconds.
+    FrameTime,
+    /// The number of entities in the world.
+    EntityCount,
+    /// The lowest, average and highest FPS over the [window](ScreenDiagsConfig::window).
+    FpsMinAvgMax,
+    /// The 1% and 0.1% low FPS over the [window](ScreenDiagsConfig::window), which is the FPS
+    /// that all but the slowest 1% or 0.1% of frames reach.
+    FpsLows,
+    /// The number of [frames](ScreenDiagsFrames) since the app started, and since the count was
+    /// reset.
+    FrameCount,
+    /// The wall-clock time since the app started, as `hh:mm:ss`.
+    Uptime,
+    /// The rate of the fixed timestep in Hz, and the number of fixed updates in the last second.
+    FixedUpdates,
+    /// The present mode of the primary window, which says whether VSync caps the FPS.
+    PresentMode,
+    /// The physical resolution and scale factor of the primary window.
+    Resolution,
+    /// The position of the cursor in the primary window, in logical pixels from the top left, and
+    /// in the world, as seen by the [camera](ScreenDiagsCamera). The world position is on the
+    /// camera's 2D plane, so it suits 2D cameras.
+    CursorPosition,
+    /// The position of the [camera](ScreenDiagsCamera), and its yaw, pitch and roll in degrees.
+    CameraTransform,
+    /// The position of the [tracked entity](ScreenDiagsTrackedEntity), such as the player.
+    TrackedPosition,
+    /// The keys, mouse buttons and gamepad buttons held down, for recordings and tutorials.
+    InputState,
+    /// The number of visible 3D meshes, and their vertices and triangles. Bevy does not yet count
+    /// draw calls, but it draws each visible mesh once per view, so the count is close.
+    MeshStats,
+    /// The number of loaded images, meshes and fonts. Other assets can be counted with a
+    /// [diagnostic](ScreenDiagsMetric::Diagnostic) row from the `AssetCountDiagnosticsPlugin`.
+    AssetCounts,
+    /// The number of archetypes, tables and component types in the world. Archetypes that keep
+    /// growing during play point to fragmentation, as from adding and removing marker components.
+    EcsCounts,
+    /// The number of visible point, spot and directional lights, and of those that cast shadows.
+    /// A sudden rise in lights, or in shadow casters, often explains a sudden drop in FPS.
+    LightCounts,
+    /// The number of audio sinks that are playing, and of all audio sinks, spatial or not.
+    #[cfg(feature = "audio")]
+    Audio,
+    /// The slowest systems over the last interval, with their average time per frame, one per
+    /// line. Needs the [system timings](ScreenDiagsSystemTimings) resource and its layer.
+    #[cfg(feature = "system-timings")]
+    SlowestSystems,
+    /// How far the [benchmark](ScreenDiagsBenchmark) has got, or the summary of its report once it
+    /// is over.
+    Benchmark,
+    /// The number of [spikes](ScreenDiagsSpikes), which are frames much longer than the average.
+    Spikes,
+    /// The share and number of frames in the [window](ScreenDiagsConfig::window) that missed a
+    /// display refresh, for a display with this refresh rate in hertz. A frame is late when it
+    /// takes more than 10% longer than a refresh interval.
+    FramePacing(u32),
+    /// The standard deviation of the frame times over the [window](ScreenDiagsConfig::window), in
+    /// milliseconds. Two builds with the same average FPS can feel very different, and this is
+    /// what tells them apart.
+    FrameTimeJitter,
+    /// The round-trip time of the renet client's connection, in milliseconds.
+    #[cfg(feature = "renet")]
+    NetworkRtt,
+    /// The share of the renet client's packets that are lost, as a percentage.
+    #[cfg(feature = "renet")]
+    NetworkPacketLoss,
+    /// The bytes per second the renet client sends and receives, in KiB/s.
+    #[cfg(feature = "renet")]
+    NetworkBandwidth,
+    /// The time Rapier's physics step took, averaged over recent frames, in milliseconds.
+    #[cfg(feature = "rapier")]
+    PhysicsStep,
+    /// The number of Rapier rigid bodies and colliders.
+    #[cfg(feature = "rapier")]
+    PhysicsBodies,
+    /// The text of the custom row with this name, registered in [ScreenDiagsRows].
+    Custom(&'static str),
+    /// The name and graphics backend of the GPU adapter, such as "Vulkan" or "Metal".
+    GpuAdapter,
+    /// The average of any diagnostic in the [DiagnosticsStore], labelled with its name and suffix.
+    /// The plugin that adds the diagnostic must be added to the app too.
+    Diagnostic(DiagnosticId),
+    /// The resident memory of the process, in MiB, read in the background.
+    #[cfg(feature = "sysinfo")]
+    Memory,
+    /// The CPU used by the process and by the whole system, as percentages of all the cores,
+    /// read in the background.
+    #[cfg(feature = "sysinfo")]
+    Cpu,
+    /// The charge of the battery, read every few seconds on Linux.
+    #[cfg(feature = "linux-device")]
+    Battery,
+    /// Whether the device is throttling to cool off, read every few seconds on Linux.
+    #[cfg(feature = "linux-device")]
+    Thermal,
+    /// The JavaScript heap in use, where the browser reports it, and the size of the WebAssembly
+    /// memory, in MiB.
+    #[cfg(target_arch = "wasm32")]
+    WasmMemory,
+    /// The time the GPU takes over each frame, from timestamp queries, in milliseconds.
+    #[cfg(feature = "gpu-timings")]
+    GpuTime,
+    /// Whether the frames are held back by the CPU, the GPU or VSync, judged from the GPU time and
+    /// the CPU time of the main world. A new verdict only shows once it has held for a second.
+    #[cfg(feature = "gpu-timings")]
+    Bottleneck,
+}
 
-fn update_text(
-    time: Res<Time>,
-    state_resource: Option<ResMut<ScreenDiagsState>>,
-    mut text_query: Query<&mut Text, With<ScreenDiagsText>>,
-    frame_counter: Res<FrameCounter>,
-) {
-    if let Some(mut state) = state_resource {
-        if state.update_now || state.timer.tick(time.delta()).just_finished() {
-            if state.timer.paused() {
-                // Time is paused so remove text
-                for mut text in text_query.iter_mut() {
-                    let value = &mut text.sections[0].value;
-                    value.clear();
-                }
-            } else {
-                for mut text in text_query.iter_mut() {
-                    let value = &mut text.sections[0].value;
-                    value.clear();
+impl ScreenDiagsMetric {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Fps => "FPS",
+            Self::FpsTarget(_) => "FPS/target",
+            Self::FrameTime => "Frame time",
+            Self::EntityCount => "Entities",
+            Self::FpsMinAvgMax => "FPS min/avg/max",
+            Self::FpsLows => "FPS 1%/0.1% low",
+            Self::FrameCount => "Frames total/since reset",
+            Self::Uptime => "Uptime",
+            Self::FixedUpdates => "Fixed Hz/last second",
+            Self::PresentMode => "Present mode",
+            Self::Resolution => "Resolution",
+            Self::CursorPosition => "Cursor x/y/world x/y",
+            Self::CameraTransform => "Camera x/y/z/yaw/pitch/roll",
+            Self::TrackedPosition => "Tracked x/y/z",
+            Self::InputState => "Input",
+            Self::MeshStats => "Meshes/vertices/triangles",
+            Self::AssetCounts => "Images/meshes/fonts",
+            Self::EcsCounts => "Archetypes/tables/components",
+            Self::LightCounts => "Point/spot/directional/shadow lights",
+            #[cfg(feature = "audio")]
+            Self::Audio => "Audio playing/sinks",
+            #[cfg(feature = "system-timings")]
+            Self::SlowestSystems => "Slowest systems",
+            Self::Benchmark => "Benchmark",
+            Self::Spikes => "Spikes",
+            Self::FramePacing(_) => "Missed vblank %/frames",
+            Self::FrameTimeJitter => "Frame time jitter",
+            #[cfg(feature = "renet")]
+            Self::NetworkRtt => "RTT",
+            #[cfg(feature = "renet")]
+            Self::NetworkPacketLoss => "Packet loss",
+            #[cfg(feature = "renet")]
+            Self::NetworkBandwidth => "Sent/received",
+            #[cfg(feature = "rapier")]
+            Self::PhysicsStep => "Physics step",
+            #[cfg(feature = "rapier")]
+            Self::PhysicsBodies => "Rigid bodies/colliders",
+            Self::Custom(name) => name,
+            Self::GpuAdapter => "GPU",
+            // The name is taken from the diagnostic when it is read
+            Self::Diagnostic(_) => "",
+            #[cfg(feature = "sysinfo")]
+            Self::Memory => "Memory",
+            #[cfg(feature = "sysinfo")]
+            Self::Cpu => "CPU process/total",
+            #[cfg(feature = "linux-device")]
+            Self::Battery => "Battery",
+            #[cfg(feature = "linux-device")]
+            Self::Thermal => "Thermal",
+            #[cfg(target_arch = "wasm32")]
+            Self::WasmMemory => "JS heap/WASM memory",
+            #[cfg(feature = "gpu-timings")]
+            Self::GpuTime => "GPU time",
+            #[cfg(feature = "gpu-timings")]
+            Self::Bottleneck => "Bottleneck",
+        }
+    }
 
-                    write!(value, "{}{:.0}", STRING_FORMAT, frame_counter.0).unwrap();
-                }
-            }
+    // Whether the metric measures how fast the frames are coming
+    #[cfg(target_arch = "wasm32")]
+    fn is_frame_rate(self) -> bool {
+        matches!(
+            self,
+            Self::Fps | Self::FpsTarget(_) | Self::FpsMinAvgMax | Self::FrameTime
+        )
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            Self::FrameTime | Self::FrameTimeJitter => "ms",
+            #[cfg(feature = "sysinfo")]
+            Self::Memory => "MiB",
+            #[cfg(feature = "sysinfo")]
+            Self::Cpu => "%",
+            #[cfg(feature = "linux-device")]
+            Self::Battery => "%",
+            #[cfg(target_arch = "wasm32")]
+            Self::WasmMemory => "MiB",
+            #[cfg(feature = "gpu-timings")]
+            Self::GpuTime => "ms",
+            #[cfg(feature = "renet")]
+            Self::NetworkRtt => "ms",
+            #[cfg(feature = "renet")]
+            Self::NetworkPacketLoss => "%",
+            #[cfg(feature = "renet")]
+            Self::NetworkBandwidth => "KiB/s",
+            #[cfg(feature = "rapier")]
+            Self::PhysicsStep => "ms",
+            _ => "",
         }
     }
-}
 
-// Get the current fps
-fn extract_fps(diagnostics: &DiagnosticsStore) -> Option<f64> {
-    diagnostics
-        .get(FrameTimeDiagnosticsPlugin::FPS)
-        .and_then(|fps| fps.average())
-}
-
-fn spawn_text(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let font = asset_server.load("fonts/screen-diags-font.ttf");
-    commands
-        .spawn(TextBundle {
-            text: Text {
-                sections: vec![TextSection {
-                    value: STRING_INITIAL.to_string(),
-                    style: TextStyle {
-                        font,
-                        font_size: FONT_SIZE,
-                        color: FONT_COLOR,
-                    },
-                }],
-                ..Default::default()
-            },
-            ..Default::default()
-        })
-        .insert(ScreenDiagsText);
+    // Get the current values, leaving the reading empty if there are none yet
+    fn sample(self, sources: &mut MetricSources, reading: &mut Reading) {
+        let diagnostics = &sources.diagnostics;
+        let smoothing = sources.config.smoothing;
+        let smoothed = |id| {
+            diagnostics
+                .get(id)
+                .and_then(|diagnostic| smoothing.smooth(diagnostic))
+        };
+        let last = |id| {
+            diagnostics
+                .get(id)
+                .and_then(|diagnostic| diagnostic.value())
+        };
+        let values = &mut reading.values;
+        match self {
+            Self::Fps | Self::FrameTime => {
+                let (id, both_label) = match self {
+                    Self::Fps => (FrameTimeDiagnosticsPlugin::FPS, "FPS smoothed/last"),
+                    _ => (
+                        FrameTimeDiagnosticsPlugin::FRAME_TIME,
+                        "Frame time smoothed/last",
+                    ),
+                };
+                match sources.config.fps_display {
+                    ScreenDiagsFpsDisplay::Smoothed => values.extend(smoothed(id)),
+                    ScreenDiagsFpsDisplay::Last => values.extend(last(id)),
+                    ScreenDiagsFpsDisplay::Both => {
+                        if let (Some(smoothed), Some(last)) = (smoothed(id), last(id)) {
+                            values.extend([smoothed, last]);
+                            reading.label.push_str(both_label);
+                        }
+                    }
+                }
+            }
+            Self::FpsTarget(target) => {
+                if let Some(fps) = smoothed(FrameTimeDiagnosticsPlugin::FPS) {
+                    let target = f64::from(target);
+                    values.extend([fps, target]);
+                    write!(
+                        reading.text,
+                        "{:.0} / {} ({:+.0})",
+                        fps,
+                        target,
+                        fps - target
+                    )
+                    .unwrap();
+                }
+            }
+            Self::EntityCount => values.extend(
+                diagnostics
+                    .get(EntityCountDiagnosticsPlugin::ENTITY_COUNT)
+                    .and_then(|count| count.value()),
+            ),
+            Self::FpsMinAvgMax => {
+                if let Some((min, avg, max)) = sources.history.fps_min_avg_max() {
+                    values.extend([min, avg, max]);
+                }
+            }
+            Self::FpsLows => {
+                if let Some((low, very_low)) = sources.history.fps_lows() {
+                    values.extend([low, very_low]);
+                }
+            }
+            Self::FrameCount => values.extend([
+                sources.frames.total() as f64,
+                sources.frames.since_reset() as f64,
+            ]),
+            Self::Uptime => {
+                let seconds = sources.time.raw_elapsed().as_secs();
+                let (hours, minutes) = (seconds / 3600, seconds / 60 % 60);
+                write!(
+                    reading.text,
+                    "{:02}:{:02}:{:02}",
+                    hours,
+                    minutes,
+                    seconds % 60
+                )
+                .unwrap();
+            }
+            Self::FixedUpdates => {
+                if let Some(fixed_time) = &sources.fixed_time {
+                    let now = sources.time.raw_elapsed();
+                    values.extend([
+                        1.0 / fixed_time.period.as_secs_f64(),
+                        sources.fixed_updates.last_second(now) as f64,
+                    ]);
+                }
+            }
+            Self::PresentMode => {
+                if let Ok(window) = sources.primary_window.get_single() {
+                    write!(reading.text, "{:?}", window.present_mode).unwrap();
+                }
+            }
+            Self::Resolution => {
+                if let Ok(window) = sources.primary_window.get_single() {
+                    let resolution = &window.resolution;
+                    write!(
+                        reading.text,
+                        "{}x{} @ {}x",
+                        resolution.physical_width(),
+                        resolution.physical_height(),
+                        resolution.scale_factor()
+                    )
+                    .unwrap();
+                }
+            }
+            Self::CursorPosition => {
+                let Some(cursor) = sources
+                    .primary_window
+                    .get_single()
+                    .ok()
+                    .and_then(Window::cursor_position)
+                else {
+                    return;
+                };
+                values.extend([cursor.x, cursor.y].map(f64::from));
+                let world = sources
+                    .camera()
+                    .and_then(|(camera, transform)| camera.viewport_to_world_2d(transform, cursor));
+                if let Some(world) = world {
+                    values.extend([world.x, world.y].map(f64::from));
+                }
+            }
+            Self::CameraTransform => {
+                if let Some((_, transform)) = sources.camera() {
+                    let (_, rotation, translation) = transform.to_scale_rotation_translation();
+                    let (yaw, pitch, roll) = rotation.to_euler(EulerRot::YXZ);
+                    values.extend(
+                        [translation.x, translation.y, translation.z]
+                            .into_iter()
+                            .chain([yaw, pitch, roll].map(f32::to_degrees))
+                            .map(f64::from),
+                    );
+                }
+            }
+            Self::TrackedPosition => {
+                if let Some(transform) = sources.tracked() {
+                    let translation = transform.translation();
+                    values.extend([translation.x, translation.y, translation.z].map(f64::from));
+                }
+            }
+            Self::InputState => {
+                // Separates the entries with spaces, without allocating a list of them
+                let text = &mut reading.text;
+                let separate = |text: &mut String| {
+                    if !text.is_empty() {
+                        text.push(' ');
+                    }
+                };
+                let keys = sources.keyboard.iter().flat_map(|keys| keys.get_pressed());
+                for key in keys {
+                    separate(text);
+                    write!(text, "{:?}", key).unwrap();
+                }
+                let buttons = sources.mouse.iter().flat_map(|mouse| mouse.get_pressed());
+                for button in buttons {
+                    separate(text);
+                    write!(text, "Mouse{:?}", button).unwrap();
+                }
+                let buttons = sources.gamepad.iter().flat_map(|pad| pad.get_pressed());
+                for button in buttons {
+                    separate(text);
+                    write!(text, "Pad{}:{:?}", button.gamepad.id, button.button_type).unwrap();
+                }
+                if text.is_empty() {
+                    text.push('-');
+                }
+            }
+            Self::MeshStats => {
+                let Some(meshes) = &sources.meshes else {
+                    return;
+                };
+                let (mut count, mut vertices, mut triangles) = (0, 0, 0);
+                for (handle, visibility) in sources.mesh_query.iter() {
+                    let Some(mesh) = meshes.get(handle) else {
+                        continue;
+                    };
+                    if !visibility.is_visible() {
+                        continue;
+                    }
+                    count += 1;
+                    vertices += mesh.count_vertices();
+                    triangles += count_triangles(mesh);
+                }
+                values.extend([count as f64, vertices as f64, triangles as f64]);
+            }
+            Self::AssetCounts => {
+                if let (Some(images), Some(meshes), Some(fonts)) =
+                    (&sources.images, &sources.meshes, &sources.fonts)
+                {
+                    values.extend([images.len() as f64, meshes.len() as f64, fonts.len() as f64]);
+                }
+            }
+            Self::EcsCounts => {
+                // Tables are never removed, so the highest id of any archetype's table counts them
+                let tables = sources
+                    .archetypes
+                    .iter()
+                    .map(|archetype| archetype.table_id().index() + 1)
+                    .max()
+                    .unwrap_or_default();
+                values.extend([
+                    sources.archetypes.len() as f64,
+                    tables as f64,
+                    sources.components.len() as f64,
+                ]);
+            }
+            Self::LightCounts => {
+                let (mut point, mut spot, mut directional, mut shadows) = (0, 0, 0, 0);
+                for (point_light, spot_light, directional_light, visibility) in
+                    sources.light_query.iter()
+                {
+                    if visibility.is_some_and(|visibility| !visibility.is_visible()) {
+                        continue;
+                    }
+                    let casts_shadows = match (point_light, spot_light, directional_light) {
+                        (Some(light), ..) => {
+                            point += 1;
+                            light.shadows_enabled
+                        }
+                        (_, Some(light), _) => {
+                            spot += 1;
+                            light.shadows_enabled
+                        }
+                        (.., Some(light)) => {
+                            directional += 1;
+                            light.shadows_enabled
+                        }
+                        _ => false,
+                    };
+                    shadows += usize::from(casts_shadows);
+                }
+                values.extend([
+                    point as f64,
+                    spot as f64,
+                    directional as f64,
+                    shadows as f64,
+                ]);
+            }
+            #[cfg(feature = "audio")]
+            Self::Audio => {
+                let sinks = sources
+                    .audio_sinks
+                    .iter()
+                    .map(|sink| !sink.is_paused() && !sink.empty());
+                let spatial_sinks = sources
+                    .spatial_audio_sinks
+                    .iter()
+                    .map(|sink| !sink.is_paused() && !sink.empty());
+                let (mut playing, mut total) = (0, 0);
+                for sink_playing in sinks.chain(spatial_sinks) {
+                    playing += usize::from(sink_playing);
+                    total += 1;
+                }
+                values.extend([playing as f64, total as f64]);
+            }
+            #[cfg(feature = "system-timings")]
+            Self::SlowestSystems => {
+                if let Some(timings) = &sources.system_timings {
+                    timings.write(&mut reading.text);
+                }
+            }
+            Self::Benchmark => {
+                if let Some(benchmark) = &sources.benchmark {
+                    benchmark.write(&mut reading.text);
+                }
+            }
+            Self::Spikes => values.push(sources.spikes.count() as f64),
+            Self::FramePacing(refresh_rate) => {
+                let interval = 1.0 / f64::from(refresh_rate.max(1));
+                if let Some((late, total)) = sources.history.late_frames(interval * 1.1) {
+                    values.extend([late as f64 * 100.0 / total as f64, late as f64]);
+                }
+            }
+            Self::FrameTimeJitter => values.extend(
+                sources
+                    .history
+                    .frame_time_stddev()
+                    .map(|stddev| stddev * 1000.0),
+            ),
+            #[cfg(feature = "renet")]
+            Self::NetworkRtt => {
+                if let Some(client) = &sources.renet_client {
+                    // renet measures the round trip in seconds
+                    values.push(client.network_info().rtt * 1000.0);
+                }
+            }
+            #[cfg(feature = "renet")]
+            Self::NetworkPacketLoss => {
+                if let Some(client) = &sources.renet_client {
+                    values.push(client.network_info().packet_loss * 100.0);
+                }
+            }
+            #[cfg(feature = "renet")]
+            Self::NetworkBandwidth => {
+                if let Some(client) = &sources.renet_client {
+                    let info = client.network_info();
+                    values.extend([
+                        info.bytes_sent_per_second / 1024.0,
+                        info.bytes_received_per_second / 1024.0,
+                    ]);
+                }
+            }
+            Self::Custom(name) => {
+                if let Some(text) = sources.custom_rows.text(name) {
+                    reading.text.push_str(text);
+                }
+            }
+            Self::GpuAdapter => {
+                if let Some(adapter) = &sources.adapter {
+                    write!(reading.text, "{} ({:?})", adapter.name, adapter.backend).unwrap();
+                }
+            }
+            Self::Diagnostic(id) => {
+                if let Some(diagnostic) = diagnostics.get(id) {
+                    reading.label.push_str(&diagnostic.name);
+                    reading.suffix.push_str(&diagnostic.suffix);
+                    values.extend(diagnostic.average());
+                }
+            }
+            #[cfg(feature = "rapier")]
+            Self::PhysicsStep => values.extend(
+                diagnostics
+                    .get(physics::PHYSICS_STEP)
+                    .and_then(|step| step.average()),
+            ),
+            #[cfg(feature = "rapier")]
+            Self::PhysicsBodies => {
+                let count = |id| diagnostics.get(id).and_then(|count| count.value());
+                if let (Some(bodies), Some(colliders)) =
+                    (count(physics::RIGID_BODIES), count(physics::COLLIDERS))
+                {
+                    values.extend([bodies, colliders]);
+                }
+            }
+            #[cfg(feature = "sysinfo")]
+            Self::Memory => values.extend(
+                diagnostics
+                    .get(system::MEMORY)
+                    .and_then(|memory| memory.value()),
+            ),
+            #[cfg(feature = "sysinfo")]
+            Self::Cpu => {
+                let cpu = |id| diagnostics.get(id).and_then(|cpu| cpu.value());
+                if let (Some(process), Some(total)) =
+                    (cpu(system::CPU_PROCESS), cpu(system::CPU_TOTAL))
+                {
+                    values.extend([process, total]);
+                }
+            }
+            #[cfg(feature = "linux-device")]
+            Self::Battery => values.extend(
+                diagnostics
+                    .get(device::BATTERY)
+                    .and_then(|battery| battery.value()),
+            ),
+            #[cfg(feature = "linux-device")]
+            Self::Thermal => {
+                if let Some(level) = diagnostics
+                    .get(device::THERMAL)
+                    .and_then(|thermal| thermal.value())
+                {
+                    reading.text.push_str(device::ThermalState::name(level));
+                }
+            }
+            #[cfg(target_arch = "wasm32")]
+            Self::WasmMemory => match web::js_heap_used() {
+                Some(heap) => values.extend([heap, web::wasm_memory()]),
+                None => {
+                    reading.label.push_str("WASM memory");
+                    values.push(web::wasm_memory());
+                }
+            },
+            #[cfg(feature = "gpu-timings")]
+            Self::GpuTime => values.extend(
+                diagnostics
+                    .get(gpu_timings::GPU_TIME)
+                    .and_then(|gpu_time| gpu_time.average()),
+            ),
+            #[cfg(feature = "gpu-timings")]
+            Self::Bottleneck => {
+                if let Some(level) = diagnostics
+                    .get(bottleneck::BOTTLENECK)
+                    .and_then(|bottleneck| bottleneck.value())
+                {
+                    reading.text.push_str(bottleneck::Bottleneck::name(level));
+                }
+            }
+        }
+    }
+}
+
+// The number of triangles drawn for the mesh
+fn count_triangles(mesh: &Mesh) -> usize {
+    let points = match mesh.indices() {
+        Some(indices) => indices.len(),
+        None => mesh.count_vertices(),
+    };
+    match mesh.primitive_topology() {
+        PrimitiveTopology::TriangleList => points / 3,
+        PrimitiveTopology::TriangleStrip => points.saturating_sub(2),
+        _ => 0,
+    }
+}
+
+// Everything the metrics are sampled from
+#[derive(SystemParam)]
+struct MetricSources<'w, 's> {
+    diagnostics: Res<'w, DiagnosticsStore>,
+    config: Res<'w, ScreenDiagsConfig>,
+    history: ResMut<'w, ScreenDiagsHistory>,
+    custom_rows: Res<'w, ScreenDiagsRows>,
+    spikes: Res<'w, ScreenDiagsSpikes>,
+    frames: Res<'w, ScreenDiagsFrames>,
+    time: Res<'w, Time>,
+    fixed_time: Option<Res<'w, FixedTime>>,
+    fixed_updates: Res<'w, FixedUpdateHistory>,
+    primary_window: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    mesh_query: Query<'w, 's, (&'static Handle<Mesh>, &'static ComputedVisibility)>,
+    #[allow(clippy::type_complexity)]
+    light_query: Query<
+        'w,
+        's,
+        (
+            Option<&'static PointLight>,
+            Option<&'static SpotLight>,
+            Option<&'static DirectionalLight>,
+            Option<&'static ComputedVisibility>,
+        ),
+        Or<(With<PointLight>, With<SpotLight>, With<DirectionalLight>)>,
+    >,
+    // The assets are missing when rendering is disabled
+    meshes: Option<Res<'w, Assets<Mesh>>>,
+    images: Option<Res<'w, Assets<Image>>>,
+    fonts: Option<Res<'w, Assets<Font>>>,
+    archetypes: &'w Archetypes,
+    components: &'w Components,
+    #[cfg(feature = "audio")]
+    audio_sinks: Query<'w, 's, &'static AudioSink>,
+    #[cfg(feature = "audio")]
+    spatial_audio_sinks: Query<'w, 's, &'static SpatialAudioSink>,
+    benchmark: Option<Res<'w, ScreenDiagsBenchmark>>,
+    baseline: Option<Res<'w, ScreenDiagsBaseline>>,
+    labels: Option<Res<'w, ScreenDiagsLabels>>,
+    #[cfg(feature = "system-timings")]
+    system_timings: Option<Res<'w, ScreenDiagsSystemTimings>>,
+    // Missing until the client connects
+    #[cfg(feature = "renet")]
+    renet_client: Option<Res<'w, RenetClient>>,
+    // Missing when rendering is disabled
+    adapter: Option<Res<'w, RenderAdapterInfo>>,
+    cameras: Query<
+        'w,
+        's,
+        (
+            &'static Camera,
+            &'static GlobalTransform,
+            Option<&'static ScreenDiagsCamera>,
+        ),
+    >,
+    tracked_entity: Res<'w, ScreenDiagsTrackedEntity>,
+    // Missing without the input plugin
+    keyboard: Option<Res<'w, Input<KeyCode>>>,
+    mouse: Option<Res<'w, Input<MouseButton>>>,
+    gamepad: Option<Res<'w, Input<GamepadButton>>>,
+    transforms: Query<'w, 's, &'static GlobalTransform>,
+    tracked_markers: Query<'w, 's, &'static GlobalTransform, With<ScreenDiagsTracked>>,
+}
+
+impl MetricSources<'_, '_> {
+    // The camera with the ScreenDiagsCamera marker, or else the active camera drawn last
+    fn camera(&self) -> Option<(&Camera, &GlobalTransform)> {
+        let marked = self.cameras.iter().find(|(.., marker)| marker.is_some());
+        marked
+            .or_else(|| {
+                self.cameras
+                    .iter()
+                    .filter(|(camera, ..)| camera.is_active)
+                    .max_by_key(|(camera, ..)| camera.order)
+            })
+            .map(|(camera, transform, _)| (camera, transform))
+    }
+
+    // The transform of the tracked entity, or else of the first with the ScreenDiagsTracked marker
+    fn tracked(&self) -> Option<&GlobalTransform> {
+        match self.tracked_entity.0 {
+            Some(entity) => self.transforms.get(entity).ok(),
+            None => self.tracked_markers.iter().next(),
+        }
+    }
+}
+
+/// A function that writes one value of a row, such as `|text, fps| write!(text, "{:.1} fps", fps)`.
+///
+/// It writes into the text of the row, rather than returning a new string, so that updates do not
+/// allocate.
+pub type ScreenDiagsFormatter = fn(&mut String, f64) -> std::fmt::Result;
+
+/// A line of the diagnostics display.
+#[derive(Reflect, Clone, Debug)]
+pub struct ScreenDiagsRow {
+    /// The value shown on the line.
+    pub metric: ScreenDiagsMetric,
+    /// The number of decimal places shown.
+    pub precision: usize,
+    /// Whether the values are formatted to suit the suffix of the metric in place of the
+    /// [precision](ScreenDiagsRow::precision): times with 2 decimal places, percentages and sizes
+    /// with 1, and counts as whole numbers with thousands separators.
+    /// [Diagnostic](ScreenDiagsRow::diagnostic) rows have it until a precision is set.
+    pub auto_format: bool,
+    /// The function that writes each value of the row in place of the
+    /// [precision](ScreenDiagsRow::precision) and suffix, if any. Rows of text are not formatted.
+    #[reflect(ignore)]
+    pub formatter: Option<ScreenDiagsFormatter>,
+    /// The text after the values, such as `ms`, in place of the metric's own suffix. An empty
+    /// suffix leaves it out.
+    pub suffix: Option<String>,
+    /// The text before the values, in place of the metric's own label, such as `FPS`. It is not
+    /// [translated](ScreenDiagsLabels).
+    pub label: Option<String>,
+}
+
+// Formatters are compared by address, which is the best that can be done for a function. The same
+// function may compare unequal across codegen units, which only costs a rebuild of the overlay.
+impl PartialEq for ScreenDiagsRow {
+    fn eq(&self, other: &Self) -> bool {
+        self.metric == other.metric
+            && self.precision == other.precision
+            && self.auto_format == other.auto_format
+            && self.formatter.map(|formatter| formatter as usize)
+                == other.formatter.map(|formatter| formatter as usize)
+            && self.suffix == other.suffix
+            && self.label == other.label
+    }
+}
+
+impl ScreenDiagsRow {
+    // A row showing the metric with this many decimal places, and nothing else changed
+    const fn new(metric: ScreenDiagsMetric, precision: usize) -> Self {
+        Self {
+            metric,
+            precision,
+            auto_format: false,
+            formatter: None,
+            suffix: None,
+            label: None,
+        }
+    }
+
+    /// A row showing the FPS.
+    pub fn fps() -> Self {
+        Self::new(ScreenDiagsMetric::Fps, 0)
+    }
+
+    /// A row showing the FPS against a target, such as `60 / 144 (-84)`, coloured by how close
+    /// it is with the [target colours](ScreenDiagsStyle::target_colors).
+    pub fn fps_target(target: u32) -> Self {
+        Self::new(ScreenDiagsMetric::FpsTarget(target), 0)
+    }
+
+    /// A row showing the frame time in milliseconds.
+    pub fn frame_time() -> Self {
+        Self::new(ScreenDiagsMetric::FrameTime, 2)
+    }
+
+    /// A row showing the number of entities.
+    pub fn entity_count() -> Self {
+        Self::new(ScreenDiagsMetric::EntityCount, 0)
+    }
+
+    /// A row showing the lowest, average and highest FPS over the recent window.
+    pub fn fps_min_avg_max() -> Self {
+        Self::new(ScreenDiagsMetric::FpsMinAvgMax, 0)
+    }
+
+    /// A row showing the 1% and 0.1% low FPS over the recent window.
+    pub fn fps_lows() -> Self {
+        Self::new(ScreenDiagsMetric::FpsLows, 0)
+    }
+
+    /// A row showing the number of frames since the app started, and since the count was reset.
+    pub fn frame_count() -> Self {
+        Self::new(ScreenDiagsMetric::FrameCount, 0)
+    }
+
+    /// A row showing the time since the app started.
+    pub fn uptime() -> Self {
+        Self::new(ScreenDiagsMetric::Uptime, 0)
+    }
+
+    /// A row showing the rate of the fixed timestep, and the number of fixed updates in the last
+    /// second.
+    pub fn fixed_updates() -> Self {
+        Self::new(ScreenDiagsMetric::FixedUpdates, 0)
+    }
+
+    /// A row showing the present mode of the primary window.
+    pub fn present_mode() -> Self {
+        Self::new(ScreenDiagsMetric::PresentMode, 0)
+    }
+
+    /// A row showing the physical resolution and scale factor of the primary window.
+    pub fn resolution() -> Self {
+        Self::new(ScreenDiagsMetric::Resolution, 0)
+    }
+
+    /// A row showing the position of the cursor in the window and in the world.
+    pub fn cursor_position() -> Self {
+        Self::new(ScreenDiagsMetric::CursorPosition, 1)
+    }
+
+    /// A row showing the position and rotation of the camera.
+    pub fn camera_transform() -> Self {
+        Self::new(ScreenDiagsMetric::CameraTransform, 1)
+    }
+
+    /// A row showing the position of the [tracked entity](ScreenDiagsTrackedEntity).
+    pub fn tracked_position() -> Self {
+        Self::new(ScreenDiagsMetric::TrackedPosition, 1)
+    }
+
+    /// A row showing the keys, mouse buttons and gamepad buttons held down.
+    pub fn input_state() -> Self {
+        Self::new(ScreenDiagsMetric::InputState, 0)
+    }
+
+    /// A row showing the number of visible meshes, and their vertices and triangles.
+    pub fn mesh_stats() -> Self {
+        Self::new(ScreenDiagsMetric::MeshStats, 0)
+    }
+
+    /// A row showing the number of loaded images, meshes and fonts.
+    pub fn asset_counts() -> Self {
+        Self::new(ScreenDiagsMetric::AssetCounts, 0)
+    }
+
+    /// A row showing the number of archetypes, tables and component types.
+    pub fn ecs_counts() -> Self {
+        Self::new(ScreenDiagsMetric::EcsCounts, 0)
+    }
+
+    /// A row showing the number of visible point, spot and directional lights, and of those that
+    /// cast shadows.
+    pub fn light_counts() -> Self {
+        Self::new(ScreenDiagsMetric::LightCounts, 0)
+    }
+
+    /// A row showing the number of audio sinks that are playing, and of all audio sinks.
+    #[cfg(feature = "audio")]
+    pub fn audio() -> Self {
+        Self::new(ScreenDiagsMetric::Audio, 0)
+    }
+
+    /// A row showing the slowest systems.
+    #[cfg(feature = "system-timings")]
+    pub fn slowest_systems() -> Self {
+        Self::new(ScreenDiagsMetric::SlowestSystems, 0)
+    }
+
+    /// A row showing the progress of the benchmark, then its report.
+    pub fn benchmark() -> Self {
+        Self::new(ScreenDiagsMetric::Benchmark, 0)
+    }
+
+    /// A row showing the number of spikes.
+    pub fn spikes() -> Self {
+        Self::new(ScreenDiagsMetric::Spikes, 0)
+    }
+
+    /// A row showing how many frames missed a refresh of a display with this refresh rate in
+    /// hertz. Bevy does not tell the refresh rate of the monitor, so it must be given.
+    pub fn frame_pacing(refresh_rate: u32) -> Self {
+        Self::new(ScreenDiagsMetric::FramePacing(refresh_rate), 1)
+    }
+
+    /// A row showing the standard deviation of the frame times in milliseconds.
+    pub fn frame_time_jitter() -> Self {
+        Self::new(ScreenDiagsMetric::FrameTimeJitter, 2)
+    }
+
+    /// A row showing the round-trip time of the renet client's connection.
+    #[cfg(feature = "renet")]
+    pub fn network_rtt() -> Self {
+        Self::new(ScreenDiagsMetric::NetworkRtt, 0)
+    }
+
+    /// A row showing the share of the renet client's packets that are lost.
+    #[cfg(feature = "renet")]
+    pub fn network_packet_loss() -> Self {
+        Self::new(ScreenDiagsMetric::NetworkPacketLoss, 1)
+    }
+
+    /// A row showing the bytes per second the renet client sends and receives.
+    #[cfg(feature = "renet")]
+    pub fn network_bandwidth() -> Self {
+        Self::new(ScreenDiagsMetric::NetworkBandwidth, 1)
+    }
+
+    /// A row showing the time Rapier's physics step took.
+    #[cfg(feature = "rapier")]
+    pub fn physics_step() -> Self {
+        Self::new(ScreenDiagsMetric::PhysicsStep, 2)
+    }
+
+    /// A row showing the number of Rapier rigid bodies and colliders.
+    #[cfg(feature = "rapier")]
+    pub fn physics_bodies() -> Self {
+        Self::new(ScreenDiagsMetric::PhysicsBodies, 0)
+    }
+
+    /// A row showing the resident memory of the process.
+    #[cfg(feature = "sysinfo")]
+    pub fn memory() -> Self {
+        Self::new(ScreenDiagsMetric::Memory, 1)
+    }
+
+    /// A row showing the CPU used by the process and by the whole system.
+    #[cfg(feature = "sysinfo")]
+    pub fn cpu() -> Self {
+        Self::new(ScreenDiagsMetric::Cpu, 0)
+    }
+
+    /// A row showing the charge of the battery.
+    #[cfg(feature = "linux-device")]
+    pub fn battery() -> Self {
+        Self::new(ScreenDiagsMetric::Battery, 0)
+    }
+
+    /// A row showing whether the device is throttling to cool off.
+    #[cfg(feature = "linux-device")]
+    pub fn thermal() -> Self {
+        Self::new(ScreenDiagsMetric::Thermal, 0)
+    }
+
+    /// A row showing the JavaScript heap and WebAssembly memory of a web build.
+    #[cfg(target_arch = "wasm32")]
+    pub fn wasm_memory() -> Self {
+        Self::new(ScreenDiagsMetric::WasmMemory, 1)
+    }
+
+    /// A row showing the time the GPU takes over each frame.
+    #[cfg(feature = "gpu-timings")]
+    pub fn gpu_time() -> Self {
+        Self::new(ScreenDiagsMetric::GpuTime, 2)
+    }
+
+    /// A row showing whether the frames are held back by the CPU, the GPU or VSync.
+    #[cfg(feature = "gpu-timings")]
+    pub fn bottleneck() -> Self {
+        Self::new(ScreenDiagsMetric::Bottleneck, 0)
+    }
+
+    /// A row showing the diagnostic with this id, with its name, and its values formatted to suit
+    /// its suffix.
+    pub fn diagnostic(id: DiagnosticId) -> Self {
+        Self {
+            auto_format: true,
+            ..Self::new(ScreenDiagsMetric::Diagnostic(id), 2)
+        }
+    }
+
+    /// A row showing the name and backend of the GPU adapter.
+    pub fn gpu_adapter() -> Self {
+        Self::new(ScreenDiagsMetric::GpuAdapter, 0)
+    }
+
+    /// A row showing the custom row with this name, registered in [ScreenDiagsRows].
+    pub fn custom(name: &'static str) -> Self {
+        Self::new(ScreenDiagsMetric::Custom(name), 0)
+    }
+
+    /// Shows this many decimal places.
+    pub fn with_precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self.auto_format = false;
+        self
+    }
+
+    /// Puts this text after the values, in place of the metric's own suffix.
+    pub fn with_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.suffix = Some(suffix.into());
+        self
+    }
+
+    /// Shows this text before the values, in place of the metric's own label.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Writes each value of the row with this function.
+    pub fn with_formatter(mut self, formatter: ScreenDiagsFormatter) -> Self {
+        self.formatter = Some(formatter);
+        self
+    }
+}
+
+/// The diagnostics configuration resource, saying what is displayed.
+///
+/// The rows and the interval can be changed at any time, and the display will follow at once.
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct ScreenDiagsConfig {
+    /// The lines of the display, from top to bottom.
+    pub rows: Vec<ScreenDiagsRow>,
+    /// How far back the statistics over recent frames look.
+    pub window: Duration,
+    /// How often the display updates. This must not be zero.
+    pub interval: Duration,
+    /// Whether to leave the frames out of the statistics over recent frames while the window is
+    /// unfocused or minimized, when the OS throttles them. The next frame is marked as
+    /// [after the gap](ScreenDiagsFrameSample::after_gap).
+    pub pause_when_unfocused: bool,
+    /// How the FPS, FPS/target and frame time rows and the [FrameCounter] are smoothed. Like the
+    /// window, this is always the one in the resource.
+    pub smoothing: ScreenDiagsSmoothing,
+    /// Whether the FPS and frame time rows show the smoothed value, the value of the last frame,
+    /// or both. Like the window, this is always the one in the resource.
+    pub fps_display: ScreenDiagsFpsDisplay,
+}
+
+impl ScreenDiagsConfig {
+    /// Whether any row shows the metric.
+    pub fn shows(&self, metric: ScreenDiagsMetric) -> bool {
+        self.rows.iter().any(|row| row.metric == metric)
+    }
+}
+
+impl Default for ScreenDiagsConfig {
+    fn default() -> Self {
+        Self {
+            rows: vec![ScreenDiagsRow::fps()],
+            window: HISTORY_WINDOW,
+            interval: UPDATE_INTERVAL,
+            pause_when_unfocused: false,
+            smoothing: ScreenDiagsSmoothing::default(),
+            fps_display: ScreenDiagsFpsDisplay::default(),
+        }
+    }
+}
+
+/// Which values the FPS and frame time rows show.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScreenDiagsFpsDisplay {
+    /// The value [smoothed](ScreenDiagsSmoothing) over the recent frames.
+    #[default]
+    Smoothed,
+    /// The value of the last frame alone, which shows single-frame hitches that smoothing hides.
+    Last,
+    /// The smoothed value, then the value of the last frame.
+    Both,
+}
+
+/// How the FPS and frame time rows smooth the measurements of the frames, which Bevy keeps for the
+/// last 20 frames.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq)]
+pub enum ScreenDiagsSmoothing {
+    /// Bevy's own average of the diagnostic, over all the frames it keeps.
+    #[default]
+    Average,
+    /// The average over this many of the latest frames, which follows changes faster when it is
+    /// shorter.
+    Window(usize),
+    /// An exponential moving average, where each frame counts for this much, between 0 and 1, and
+    /// the frames before it for the rest. Higher values follow changes faster.
+    Exponential(f64),
+}
+
+impl ScreenDiagsSmoothing {
+    // The smoothed value of the measurements of a diagnostic, if it has any
+    fn smooth(self, diagnostic: &Diagnostic) -> Option<f64> {
+        match self {
+            Self::Average => diagnostic.average(),
+            Self::Window(frames) => {
+                let skipped = diagnostic.history_len().saturating_sub(frames.max(1));
+                let (total, count) = diagnostic
+                    .values()
+                    .skip(skipped)
+                    .fold((0.0, 0_u32), |(total, count), value| {
+                        (total + value, count + 1)
+                    });
+                (count > 0).then(|| total / f64::from(count))
+            }
+            Self::Exponential(alpha) => {
+                let alpha = alpha.clamp(0.0, 1.0);
+                diagnostic
+                    .values()
+                    .fold(None, |smoothed, value| match smoothed {
+                        Some(smoothed) => Some(alpha * value + (1.0 - alpha) * smoothed),
+                        None => Some(*value),
+                    })
+            }
+        }
+    }
+}
+
+/// The resource saying which rows of the [config](ScreenDiagsConfig) are hidden at runtime, such
+/// as from a debug menu. Hidden rows are left out of the overlays without rebuilding them, and are
+/// no longer sampled.
+#[derive(Resource, Reflect, Clone, Debug, Default)]
+#[reflect(Resource)]
+pub struct ScreenDiagsRowsConfig {
+    /// The metrics of the hidden rows.
+    pub hidden: Vec<ScreenDiagsMetric>,
+}
+
+impl ScreenDiagsRowsConfig {
+    /// Hides the rows showing the metric.
+    pub fn hide(&mut self, metric: ScreenDiagsMetric) {
+        if self.is_shown(metric) {
+            self.hidden.push(metric);
+        }
+    }
+
+    /// Shows the rows showing the metric again.
+    pub fn show(&mut self, metric: ScreenDiagsMetric) {
+        self.hidden.retain(|hidden| *hidden != metric);
+    }
+
+    /// Hides the rows showing the metric if they are shown, or shows them if they are hidden.
+    pub fn toggle(&mut self, metric: ScreenDiagsMetric) {
+        if self.is_shown(metric) {
+            self.hide(metric);
+        } else {
+            self.show(metric);
+        }
+    }
+
+    /// Whether the rows showing the metric are shown.
+    pub fn is_shown(&self, metric: ScreenDiagsMetric) -> bool {
+        !self.hidden.contains(&metric)
+    }
+}
+
+/// Send this event to flip the FPS display on or off.
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct ScreenDiagsToggle;
+
+/// Sent when the FPS display is enabled or disabled, however that happened.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ScreenDiagsVisibilityChanged {
+    /// Whether the display is now enabled.
+    pub enabled: bool,
+}
+
+/// Insert this resource to toggle the FPS display with a key chord, or a gamepad button chord.
+///
+/// The display toggles when all of the keys are held down, as soon as the last one is pressed, and
+/// likewise for the gamepad buttons on any one gamepad.
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct ScreenDiagsHotkey {
+    /// The keys in the chord.
+    pub keys: Vec<KeyCode>,
+    /// The gamepad buttons in the chord, such as Select and Start, for builds without a keyboard.
+    /// Empty by default.
+    pub gamepad_buttons: Vec<GamepadButtonType>,
+}
+
+impl Default for ScreenDiagsHotkey {
+    fn default() -> Self {
+        Self {
+            keys: vec![KeyCode::F12],
+            gamepad_buttons: Vec::new(),
+        }
+    }
+}
+
+impl ScreenDiagsHotkey {
+    /// Toggles with this chord of gamepad buttons as well as the keys.
+    pub fn with_gamepad_buttons(
+        mut self,
+        buttons: impl IntoIterator<Item = GamepadButtonType>,
+    ) -> Self {
+        self.gamepad_buttons = buttons.into_iter().collect();
+        self
+    }
+}
+
+/// Insert this resource to cycle through the [display modes](ScreenDiagsDisplayMode) with a key
+/// chord, as with the F3 overlays of many games.
+///
+/// Each press moves to the next mode in the list, going back to the first after the last.
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct ScreenDiagsModeHotkey {
+    /// The keys in the chord.
+    pub keys: Vec<KeyCode>,
+    /// The modes to cycle through, in order.
+    pub modes: Vec<ScreenDiagsDisplayMode>,
+}
+
+impl Default for ScreenDiagsModeHotkey {
+    fn default() -> Self {
+        Self {
+            keys: vec![KeyCode::F3],
+            modes: vec![
+                ScreenDiagsDisplayMode::Off,
+                ScreenDiagsDisplayMode::FpsOnly,
+                ScreenDiagsDisplayMode::Verbose,
+            ],
+        }
+    }
+}
+
+// Moves to the next display mode on a press of the chord
+fn cycle_mode_on_hotkey(
+    hotkey: Res<ScreenDiagsModeHotkey>,
+    keyboard: Res<Input<KeyCode>>,
+    mut mode: ResMut<ScreenDiagsDisplayMode>,
+) {
+    let keys = hotkey.keys.iter().copied();
+    if hotkey.keys.is_empty()
+        || hotkey.modes.is_empty()
+        || !keys.clone().all(|key| keyboard.pressed(key))
+        || !keyboard.any_just_pressed(keys)
+    {
+        return;
+    }
+    // A mode that is not in the list starts the cycle again
+    let next = hotkey
+        .modes
+        .iter()
+        .position(|cycled| *cycled == *mode)
+        .map_or(0, |index| (index + 1) % hotkey.modes.len());
+    *mode = hotkey.modes[next];
+}
+
+// Turns a press of the hotkey chord, on the keyboard or a gamepad, into a toggle event
+fn toggle_on_hotkey(
+    hotkey: Res<ScreenDiagsHotkey>,
+    keyboard: Res<Input<KeyCode>>,
+    // Missing without the gamepad plugin
+    gamepads: Option<Res<Gamepads>>,
+    gamepad_buttons: Option<Res<Input<GamepadButton>>>,
+    mut events: EventWriter<ScreenDiagsToggle>,
+) {
+    let keys = hotkey.keys.iter().copied();
+    if !hotkey.keys.is_empty()
+        && keys.clone().all(|key| keyboard.pressed(key))
+        && keyboard.any_just_pressed(keys)
+    {
+        events.send(ScreenDiagsToggle);
+        return;
+    }
+
+    let (Some(gamepads), Some(pressed)) = (gamepads, gamepad_buttons) else {
+        return;
+    };
+    if hotkey.gamepad_buttons.is_empty() {
+        return;
+    }
+    let chord_pressed = gamepads.iter().any(|gamepad| {
+        let buttons = hotkey
+            .gamepad_buttons
+            .iter()
+            .map(|button| GamepadButton::new(gamepad, *button));
+        buttons.clone().all(|button| pressed.pressed(button)) && pressed.any_just_pressed(buttons)
+    });
+    if chord_pressed {
+        events.send(ScreenDiagsToggle);
+    }
+}
+
+// Applies the toggle events to the state
+fn handle_toggle_events(
+    mut events: EventReader<ScreenDiagsToggle>,
+    mut state: ResMut<ScreenDiagsState>,
+) {
+    for _ in events.iter() {
+        state.toggle();
+    }
+}
+
+// Updates the display at once when the window changes size, if it shows the resolution
+fn update_on_resize(
+    mut resized_events: EventReader<WindowResized>,
+    mut scale_events: EventReader<WindowScaleFactorChanged>,
+    config: Res<ScreenDiagsConfig>,
+    overlay_query: Query<&ScreenDiagsOverlay>,
+    mut state: ResMut<ScreenDiagsState>,
+) {
+    let resized = resized_events.iter().count() + scale_events.iter().count() > 0;
+    let shown = config.shows(ScreenDiagsMetric::Resolution)
+        || overlay_query
+            .iter()
+            .any(|overlay| overlay.config.shows(ScreenDiagsMetric::Resolution));
+    if resized && shown {
+        state.update_now = true;
+    }
+}
+
+// Announces changes to the enabled state, whether by event or by the state methods
+fn notify_visibility_changed(
+    state: Res<ScreenDiagsState>,
+    mut last_enabled: Local<Option<bool>>,
+    mut events: EventWriter<ScreenDiagsVisibilityChanged>,
+) {
+    let enabled = state.enabled();
+    if last_enabled.is_some_and(|last| last != enabled) {
+        events.send(ScreenDiagsVisibilityChanged { enabled });
+    }
+    *last_enabled = Some(enabled);
+}
+
+/// Resource to get the current FPS, [smoothed](ScreenDiagsConfig::smoothing) like the FPS row.
+#[derive(Resource, Default)]
+pub struct FrameCounter(pub f64);
+
+/// Resource counting the frames since the app started, and since the count was last reset.
+#[derive(Resource, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Resource)]
+pub struct ScreenDiagsFrames {
+    total: u64,
+    since_reset: u64,
+}
+
+impl ScreenDiagsFrames {
+    /// The number of frames since the app started.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The number of frames since the count was last reset.
+    pub fn since_reset(&self) -> u64 {
+        self.since_reset
+    }
+
+    /// Starts counting the frames since reset again from zero.
+    pub fn reset(&mut self) {
+        self.since_reset = 0;
+    }
+}
+
+// Counts the frame that has just finished
+fn count_frames(mut frames: ResMut<ScreenDiagsFrames>) {
+    frames.total += 1;
+    frames.since_reset += 1;
+}
+
+/// Resource to get the latest reading of each metric in the [config](ScreenDiagsConfig).
+#[derive(Resource, Clone, Default, Debug)]
+pub struct ScreenDiagsReadings(HashMap<ScreenDiagsMetric, Reading>);
+
+// A reading is either numbers or text, and may bring its own label and suffix
+#[derive(Clone, Default, Debug)]
+struct Reading {
+    values: Vec<f64>,
+    // The values of the baseline to compare with, if there is one
+    baseline: Vec<f64>,
+    text: String,
+    label: String,
+    // The translation of the label, if there is one
+    translated_label: String,
+    suffix: String,
+}
+
+impl ScreenDiagsReadings {
+    // Takes a new reading of the metric
+    fn sample(&mut self, metric: ScreenDiagsMetric, sources: &mut MetricSources) {
+        let reading = self.0.entry(metric).or_default();
+        reading.values.clear();
+        reading.text.clear();
+        reading.label.clear();
+        reading.translated_label.clear();
+        reading.suffix.clear();
+        reading.baseline.clear();
+        metric.sample(sources, reading);
+        // A hidden tab runs at a frame or so a second, which is no reflection of the game
+        #[cfg(target_arch = "wasm32")]
+        if web::is_throttled() && metric.is_frame_rate() {
+            reading.values.clear();
+            reading.text.clear();
+            reading.text.push_str("THROTTLED");
+        }
+        if let Some(labels) = &sources.labels {
+            let english = match reading.label.as_str() {
+                "" => metric.label(),
+                label => label,
+            };
+            labels.translate(english, &mut reading.translated_label);
+        }
+        if let Some(baseline) = &sources.baseline {
+            if !reading.values.is_empty() {
+                baseline.extend_values(metric, &mut reading.baseline);
+            }
+        }
+    }
+
+    // The label of the metric, which may come with the reading, or be translated
+    fn label(&self, metric: ScreenDiagsMetric) -> &str {
+        match self.0.get(&metric) {
+            Some(reading) if !reading.translated_label.is_empty() => &reading.translated_label,
+            Some(reading) if !reading.label.is_empty() => &reading.label,
+            _ => metric.label(),
+        }
+    }
+
+    // The suffix of the metric, which may come with the reading
+    fn suffix(&self, metric: ScreenDiagsMetric) -> &str {
+        match self.0.get(&metric) {
+            Some(reading) if !reading.suffix.is_empty() => &reading.suffix,
+            _ => metric.suffix(),
+        }
+    }
+
+    /// The latest reading of the metric, if it is being measured.
+    /// For metrics with several values, this is the first.
+    pub fn get(&self, metric: ScreenDiagsMetric) -> Option<f64> {
+        self.values(metric).first().copied()
+    }
+
+    /// All the values of the latest reading of the metric, which is empty if there are none.
+    pub fn values(&self, metric: ScreenDiagsMetric) -> &[f64] {
+        self.0
+            .get(&metric)
+            .map_or(&[], |reading| reading.values.as_slice())
+    }
+
+    // The values of the baseline to compare with the latest reading, or none
+    fn baseline(&self, metric: ScreenDiagsMetric) -> &[f64] {
+        self.0
+            .get(&metric)
+            .map_or(&[], |reading| reading.baseline.as_slice())
+    }
+
+    /// The latest reading of a metric that is text rather than numbers, such as a custom row.
+    pub fn text(&self, metric: ScreenDiagsMetric) -> Option<&str> {
+        self.0
+            .get(&metric)
+            .map(|reading| reading.text.as_str())
+            .filter(|text| !text.is_empty())
+    }
+}
+
+// Ticks the timer, and works out whether the readings are due
+fn tick_timer(
+    time: Res<Time>,
+    config: Res<ScreenDiagsConfig>,
+    mut state: ResMut<ScreenDiagsState>,
+) {
+    let state = &mut *state;
+    if config.is_changed() && state.timer.duration() != config.interval {
+        state.timer.set_duration(config.interval);
+        state.timer.reset();
+    }
+    let popped = state.timer.tick(time.delta()).just_finished();
+    state.due = popped || state.update_now || config.is_changed();
+}
+
+// Updates the frame_counter and readings when the timer pops, or when an update is requested
+fn update_frame_counter(
+    mut sources: MetricSources,
+    config: Res<ScreenDiagsConfig>,
+    mode: Res<ScreenDiagsDisplayMode>,
+    rows_config: Res<ScreenDiagsRowsConfig>,
+    state: Res<ScreenDiagsState>,
+    mut frame_counter: ResMut<FrameCounter>,
+    mut readings: ResMut<ScreenDiagsReadings>,
+) {
+    if state.due && state.enabled() {
+        frame_counter.0 = extract_fps(&sources.diagnostics, config.smoothing).unwrap_or(0.0);
+        let rows = mode.rows(&config.rows).iter();
+        for row in rows.filter(|row| rows_config.is_shown(row.metric)) {
+            readings.sample(row.metric, &mut sources);
+        }
+    }
+}
+
+// Clears the update request once everything has seen it
+fn clear_update_now(mut state: ResMut<ScreenDiagsState>) {
+    if state.update_now {
+        state.update_now = false;
+    }
+}
+
+/// The marker on the text to be updated.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct ScreenDiagsText;
+
+/// The entity whose position the [tracked position row](ScreenDiagsRow::tracked_position) shows.
+///
+/// While no entity is set, the row follows the first entity with the [ScreenDiagsTracked] marker.
+#[derive(Resource, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Resource)]
+pub struct ScreenDiagsTrackedEntity(pub Option<Entity>);
+
+impl ScreenDiagsTrackedEntity {
+    /// Track this entity, such as the player.
+    pub fn set(&mut self, entity: Entity) {
+        self.0 = Some(entity);
+    }
+
+    /// Stop tracking the entity, falling back to the [ScreenDiagsTracked] marker.
+    pub fn clear(&mut self) {
+        self.0 = None;
+    }
+}
+
+/// The marker on the entity to show in the
+/// [tracked position row](ScreenDiagsRow::tracked_position), unless another is
+/// [set](ScreenDiagsTrackedEntity::set).
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct ScreenDiagsTracked;
+
+// Get the current fps, smoothed as the FPS row is
+fn extract_fps(diagnostics: &DiagnosticsStore, smoothing: ScreenDiagsSmoothing) -> Option<f64> {
+    diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| smoothing.smooth(fps))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        diagnostic::{Diagnostic, DiagnosticId, DiagnosticMeasurement},
+        utils::Instant,
+    };
+
+    use super::*;
+
+    fn diagnostic(values: &[f64]) -> Diagnostic {
+        let mut diagnostic = Diagnostic::new(DiagnosticId::from_u128(1), "test", 20);
+        let start = Instant::now();
+        for (frame, value) in values.iter().enumerate() {
+            diagnostic.add_measurement(DiagnosticMeasurement {
+                time: start + Duration::from_millis(frame as u64),
+                value: *value,
+            });
+        }
+        diagnostic
+    }
+
+    #[test]
+    fn smooths_over_the_frames_asked_for() {
+        let values = diagnostic(&[10.0, 20.0, 30.0, 40.0]);
+        assert_eq!(ScreenDiagsSmoothing::Average.smooth(&values), Some(25.0));
+        assert_eq!(ScreenDiagsSmoothing::Window(2).smooth(&values), Some(35.0));
+        assert_eq!(ScreenDiagsSmoothing::Window(1).smooth(&values), Some(40.0));
+        assert_eq!(ScreenDiagsSmoothing::Window(0).smooth(&values), Some(40.0));
+        assert_eq!(
+            ScreenDiagsSmoothing::Window(100).smooth(&values),
+            Some(25.0)
+        );
+    }
+
+    #[test]
+    fn smooths_exponentially() {
+        let values = diagnostic(&[10.0, 20.0, 40.0]);
+        // 10, then 10 + (20 - 10) / 2 = 15, then 15 + (40 - 15) / 2 = 27.5
+        assert_eq!(
+            ScreenDiagsSmoothing::Exponential(0.5).smooth(&values),
+            Some(27.5)
+        );
+        assert_eq!(
+            ScreenDiagsSmoothing::Exponential(1.0).smooth(&values),
+            Some(40.0)
+        );
+        assert_eq!(
+            ScreenDiagsSmoothing::Exponential(0.0).smooth(&values),
+            Some(10.0)
+        );
+        assert_eq!(
+            ScreenDiagsSmoothing::Exponential(2.0).smooth(&values),
+            Some(40.0)
+        );
+    }
+
+    #[test]
+    fn adds_nothing_when_disabled() {
+        let mut app = App::new();
+        app.insert_resource(Disabled).add_plugins((
+            ScreenDiagsPlugin::default().start_hidden(),
+            ScreenDiagsUiPlugin,
+        ));
+        assert!(!app.world.contains_resource::<ScreenDiagsConfig>());
+        assert!(!app.world.contains_resource::<ScreenDiagsState>());
+        assert!(!app.world.contains_resource::<ScreenDiagsStyle>());
+        assert!(!app.world.contains_resource::<Events<ScreenDiagsToggle>>());
+        assert!(!app.is_plugin_added::<ScreenDiagsCorePlugin>());
+    }
+
+    #[test]
+    fn smooths_nothing_before_the_first_frame() {
+        let empty = diagnostic(&[]);
+        assert_eq!(ScreenDiagsSmoothing::Average.smooth(&empty), None);
+        assert_eq!(ScreenDiagsSmoothing::Window(4).smooth(&empty), None);
+        assert_eq!(ScreenDiagsSmoothing::Exponential(0.5).smooth(&empty), None);
+    }
 }
@@ -0,0 +1,73 @@
+//! Logging the diagnostics, for when nobody is looking at the screen.
+
+use bevy::{prelude::*, utils::Duration};
+
+use crate::{overlay::write_row, MetricSources, ScreenDiagsConfig, ScreenDiagsReadings};
+
+/// The log sink resource. While it exists, the rows of the [config](ScreenDiagsConfig) are logged
+/// with `info!` at its interval, formatted as on the screen.
+///
+/// The log is kept whether or not the display is enabled, or even drawn, so it also works for
+/// headless servers and CI runs. It can replace the `LogDiagnosticsPlugin`.
+#[derive(Resource, Clone, Debug)]
+pub struct ScreenDiagsLog {
+    timer: Timer,
+    readings: ScreenDiagsReadings,
+    line: String,
+    // Whether the log is written this frame
+    due: bool,
+}
+
+impl ScreenDiagsLog {
+    /// A log sink that writes once per interval.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            timer: Timer::new(interval, TimerMode::Repeating),
+            readings: ScreenDiagsReadings::default(),
+            line: String::new(),
+            due: false,
+        }
+    }
+
+    /// The time between log lines.
+    pub fn interval(&self) -> Duration {
+        self.timer.duration()
+    }
+
+    pub(crate) fn due(&self) -> bool {
+        self.due
+    }
+}
+
+impl Default for ScreenDiagsLog {
+    fn default() -> Self {
+        Self::new(crate::UPDATE_INTERVAL)
+    }
+}
+
+pub(crate) fn tick_log(time: Res<Time>, mut log: ResMut<ScreenDiagsLog>) {
+    let log = log.bypass_change_detection();
+    log.due = log.timer.tick(time.raw_delta()).just_finished();
+}
+
+// Logs one line with all of the rows
+pub(crate) fn write_log(
+    mut sources: MetricSources,
+    config: Res<ScreenDiagsConfig>,
+    mut log: ResMut<ScreenDiagsLog>,
+) {
+    if !log.due || config.rows.is_empty() {
+        return;
+    }
+
+    let ScreenDiagsLog { readings, line, .. } = log.bypass_change_detection();
+    line.clear();
+    for (index, row) in config.rows.iter().enumerate() {
+        readings.sample(row.metric, &mut sources);
+        if index > 0 {
+            line.push_str(", ");
+        }
+        write_row(line, 0, row, readings);
+    }
+    info!("{}", line);
+}
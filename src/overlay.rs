@@ -0,0 +1,1437 @@
+//! The diagnostics overlays, drawn with Bevy UI.
+
+use std::fmt::Write;
+
+use bevy::{
+    asset::LoadState,
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::*,
+    render::{camera::RenderTarget, view::RenderLayers},
+    sprite::Anchor,
+    ui::{camera_config::UiCameraConfig, FocusPolicy, UiScale},
+    window::{PrimaryWindow, WindowRef},
+};
+
+use crate::{
+    budget, graph, histogram, MetricSources, ScreenDiagsAutoHide, ScreenDiagsBudgetBar,
+    ScreenDiagsConfig, ScreenDiagsDisplayMode, ScreenDiagsFont, ScreenDiagsGraph,
+    ScreenDiagsHistogram, ScreenDiagsMetric, ScreenDiagsPosition, ScreenDiagsReadings,
+    ScreenDiagsRow, ScreenDiagsRowsConfig, ScreenDiagsScale, ScreenDiagsShadow, ScreenDiagsState,
+    ScreenDiagsStyle, ScreenDiagsText, STRING_INITIAL,
+};
+
+/// A diagnostics overlay, with its own rows, style and timer.
+///
+/// The [UI plugin](crate::ScreenDiagsUiPlugin) spawns one overlay, which follows the
+/// [config](ScreenDiagsConfig), [style](ScreenDiagsStyle), [graph](ScreenDiagsGraph),
+/// [histogram](ScreenDiagsHistogram) and [budget bar](ScreenDiagsBudgetBar) resources.
+/// To show more, spawn entities with this component, and the plugin adds the UI nodes to them.
+/// Use this component to find an overlay's UI node, for example to move it.
+///
+/// The style, graph, histogram and budget bar are read when the UI nodes are added, but the config
+/// can be changed at any time. The statistics [window](ScreenDiagsConfig::window) is always the
+/// one in the resource.
+///
+/// Disabling the display, or setting [hidden](ScreenDiagsOverlay::hidden), only hides the overlay,
+/// so that it comes back at once with its font loaded. To destroy an overlay, despawn its entity
+/// recursively.
+///
+/// Bevy UI is laid out for the primary window, and drawn by every camera that shows UI. To show an
+/// overlay in another window, set its [window](ScreenDiagsOverlay::window), or insert
+/// [ScreenDiagsEveryWindow] for one in each window. With split screen, set the
+/// [camera](ScreenDiagsOverlay::camera) to place the overlay within that camera's viewport.
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component)]
+pub struct ScreenDiagsOverlay {
+    /// What the overlay shows, and how often it updates.
+    pub config: ScreenDiagsConfig,
+    /// How the overlay looks.
+    pub style: ScreenDiagsStyle,
+    /// The bar graph next to the text, if any.
+    pub graph: Option<ScreenDiagsGraph>,
+    /// The histogram of the frame times next to the text, if any.
+    pub histogram: Option<ScreenDiagsHistogram>,
+    /// The bar of the frame time budget next to the text, if any.
+    pub budget_bar: Option<ScreenDiagsBudgetBar>,
+    /// Whether the overlay is hidden, even while the display is enabled. It keeps its UI nodes,
+    /// but is not updated.
+    pub hidden: bool,
+    /// The camera whose viewport the overlay is placed in. If this is `None`, the overlay is placed
+    /// in the viewport of the camera with the [ScreenDiagsCamera] marker, if any, and otherwise in
+    /// the window.
+    pub camera: Option<Entity>,
+    /// The window the overlay is shown in. If this is `None` or the primary window, the overlay is
+    /// drawn with Bevy UI. In another window it is drawn as 2D text, by a camera of its own on the
+    /// [window layer](ScreenDiagsOverlay::WINDOW_LAYER), in the corner of the style's position and
+    /// without the panel, graph, histogram or budget bar. It is despawned when its window closes.
+    pub window: Option<Entity>,
+    timer: Timer,
+    #[reflect(ignore)]
+    readings: ScreenDiagsReadings,
+    // Whether the overlay was updated this frame
+    #[reflect(ignore)]
+    updated: bool,
+    // The line being written, kept to compare with the text before changing it
+    #[reflect(ignore)]
+    line: String,
+    // How far the overlay has faded in, from 0 to 1
+    #[reflect(ignore)]
+    opacity: f32,
+    // Whether the opacity changed this frame
+    #[reflect(ignore)]
+    faded: bool,
+    // Whether the font of the style has loaded, before which the overlay is hidden
+    #[reflect(ignore)]
+    font_loaded: bool,
+}
+
+impl ScreenDiagsOverlay {
+    /// The render layer of the text and cameras of the overlays in windows other than the primary
+    /// one, which keeps them out of the app's own cameras. Leave it free for them.
+    pub const WINDOW_LAYER: u8 = RenderLayers::TOTAL_LAYERS as u8 - 1;
+
+    /// An overlay with this config and style, and no graph.
+    pub fn new(config: ScreenDiagsConfig, style: ScreenDiagsStyle) -> Self {
+        let timer = Timer::new(config.interval, TimerMode::Repeating);
+        Self {
+            config,
+            style,
+            graph: None,
+            histogram: None,
+            budget_bar: None,
+            hidden: false,
+            camera: None,
+            window: None,
+            timer,
+            readings: ScreenDiagsReadings::default(),
+            updated: false,
+            line: String::new(),
+            opacity: 1.0,
+            faded: false,
+            font_loaded: false,
+        }
+    }
+
+    /// Adds a bar graph next to the text.
+    pub fn with_graph(mut self, graph: ScreenDiagsGraph) -> Self {
+        self.graph = Some(graph);
+        self
+    }
+
+    /// Adds a histogram of the frame times next to the text.
+    pub fn with_histogram(mut self, histogram: ScreenDiagsHistogram) -> Self {
+        self.histogram = Some(histogram);
+        self
+    }
+
+    /// Adds a bar of the frame time budget next to the text.
+    pub fn with_budget_bar(mut self, budget_bar: ScreenDiagsBudgetBar) -> Self {
+        self.budget_bar = Some(budget_bar);
+        self
+    }
+
+    /// Shows the overlay in this window.
+    pub fn with_window(mut self, window: Entity) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// The readings currently shown by the overlay.
+    pub fn readings(&self) -> &ScreenDiagsReadings {
+        &self.readings
+    }
+
+    pub(crate) fn updated(&self) -> bool {
+        self.updated
+    }
+
+    pub(crate) fn faded(&self) -> bool {
+        self.faded
+    }
+
+    pub(crate) fn opacity(&self) -> f32 {
+        self.opacity
+    }
+}
+
+impl Default for ScreenDiagsOverlay {
+    fn default() -> Self {
+        Self::new(ScreenDiagsConfig::default(), ScreenDiagsStyle::default())
+    }
+}
+
+/// A marker that draws an [overlay](ScreenDiagsOverlay) in the world, as 2D text, instead of on
+/// the screen.
+///
+/// Spawn it with the overlay, and with a `Transform` to place the text, usually as a child of the
+/// entity it should follow. The position, panel and graph of the style are not used in the world.
+/// Bevy only draws 2D text with 2D cameras, so in a 3D scene the text needs a 2D camera on top.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component)]
+pub struct ScreenDiagsWorldSpace;
+
+/// A marker for the camera whose viewport the overlays are placed in, unless they name their own
+/// [camera](ScreenDiagsOverlay::camera).
+#[derive(Component, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component)]
+pub struct ScreenDiagsCamera;
+
+/// Insert this resource to spawn an overlay in each window other than the primary one as it opens,
+/// with the config and style of the resources at the time. Like other overlays in a
+/// [window](ScreenDiagsOverlay::window), they are despawned when their window closes.
+#[derive(Resource, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Resource)]
+pub struct ScreenDiagsEveryWindow;
+
+// How far apart the overlays in other windows are in the world, which is further than any window
+// is wide, so that each camera only sees its own overlay
+const WINDOW_REGION: f32 = 100_000.0;
+
+// The order of the cameras of the overlays in other windows, after the windows' own cameras
+const WINDOW_CAMERA_ORDER: isize = 1000;
+
+/// Spawns an overlay showing the rows of the config, in the default style but placed
+/// [inline](ScreenDiagsPosition::Inline), and returns its entity. Make it a child of a node of the
+/// app's own UI, such as a debug panel, to show the diagnostics there.
+///
+/// The [UI plugin](crate::ScreenDiagsUiPlugin) adds the UI nodes of the overlay. To change its
+/// style, change the style of its [ScreenDiagsOverlay].
+pub fn spawn_overlay(commands: &mut Commands, config: &ScreenDiagsConfig) -> Entity {
+    let style = ScreenDiagsStyle {
+        position: ScreenDiagsPosition::Inline,
+        z_index: ZIndex::Local(0),
+        ..Default::default()
+    };
+    commands
+        .spawn(ScreenDiagsOverlay::new(config.clone(), style))
+        .id()
+}
+
+/// Despawns an overlay, such as one made with [spawn_overlay], along with its UI nodes.
+pub fn despawn_overlay(commands: &mut Commands, overlay: Entity) {
+    commands.entity(overlay).despawn_recursive();
+}
+
+// The marker on the overlay that follows the resources
+#[derive(Component)]
+pub(crate) struct PrimaryOverlay;
+
+// The marker on an overlay whose UI nodes have been added
+#[derive(Component)]
+pub(crate) struct OverlayNodes;
+
+// The marker on a copy of a text drawn behind it as its shadow
+#[derive(Component)]
+pub(crate) struct TextShadow;
+
+// The shadows of a text, which are given the same sections
+#[derive(Component)]
+pub(crate) struct Shadowed(Vec<Entity>);
+
+// A node that the overlay it belongs to is dragged by
+#[derive(Component)]
+pub(crate) struct DragHandle;
+
+// The column of the rows that a text shows, when they are laid out in columns
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OverlayColumn {
+    Labels,
+    Values,
+}
+
+pub(crate) fn spawn_primary_overlay(
+    mut commands: Commands,
+    config: Res<ScreenDiagsConfig>,
+    style: Res<ScreenDiagsStyle>,
+    graph: Option<Res<ScreenDiagsGraph>>,
+    histogram: Option<Res<ScreenDiagsHistogram>>,
+    budget_bar: Option<Res<ScreenDiagsBudgetBar>>,
+) {
+    let mut overlay = ScreenDiagsOverlay::new(config.clone(), style.clone());
+    overlay.graph = graph.as_deref().cloned();
+    overlay.histogram = histogram.as_deref().cloned();
+    overlay.budget_bar = budget_bar.as_deref().cloned();
+    commands.spawn((overlay, PrimaryOverlay));
+}
+
+// Shows the primary overlay again, or spawns it the first time
+pub(crate) fn show_primary_overlay(
+    commands: Commands,
+    config: Res<ScreenDiagsConfig>,
+    style: Res<ScreenDiagsStyle>,
+    graph: Option<Res<ScreenDiagsGraph>>,
+    histogram: Option<Res<ScreenDiagsHistogram>>,
+    budget_bar: Option<Res<ScreenDiagsBudgetBar>>,
+    mut overlay_query: Query<&mut ScreenDiagsOverlay, With<PrimaryOverlay>>,
+) {
+    if overlay_query.is_empty() {
+        spawn_primary_overlay(commands, config, style, graph, histogram, budget_bar);
+        return;
+    }
+    for mut overlay in overlay_query.iter_mut() {
+        overlay.hidden = false;
+    }
+}
+
+// Hides the primary overlay, keeping its UI nodes for when it is shown again
+pub(crate) fn hide_primary_overlay(
+    mut overlay_query: Query<&mut ScreenDiagsOverlay, With<PrimaryOverlay>>,
+) {
+    for mut overlay in overlay_query.iter_mut() {
+        overlay.hidden = true;
+    }
+}
+
+// Copies changes to the config and style resources into the primary overlay
+pub(crate) fn sync_primary_overlay(
+    mut commands: Commands,
+    config: Res<ScreenDiagsConfig>,
+    style: Res<ScreenDiagsStyle>,
+    graph: Option<Res<ScreenDiagsGraph>>,
+    histogram: Option<Res<ScreenDiagsHistogram>>,
+    budget_bar: Option<Res<ScreenDiagsBudgetBar>>,
+    mut overlay_query: Query<(Entity, &mut ScreenDiagsOverlay), With<PrimaryOverlay>>,
+) {
+    // The style is only read when the UI nodes are built, so build them again
+    if style.is_changed() && !style.is_added() {
+        for (entity, mut overlay) in overlay_query.iter_mut() {
+            overlay.config = config.clone();
+            overlay.style = style.clone();
+            overlay.graph = graph.as_deref().cloned();
+            overlay.histogram = histogram.as_deref().cloned();
+            overlay.budget_bar = budget_bar.as_deref().cloned();
+            commands
+                .entity(entity)
+                .despawn_descendants()
+                .remove::<OverlayNodes>();
+        }
+        return;
+    }
+    if !config.is_changed() {
+        return;
+    }
+
+    for (_, mut overlay) in overlay_query.iter_mut() {
+        overlay.config = config.clone();
+    }
+}
+
+// Builds the UI nodes of the overlays that ignore the UI scale again when it changes
+pub(crate) fn rebuild_on_ui_scale(
+    mut commands: Commands,
+    ui_scale: Option<Res<UiScale>>,
+    overlay_query: Query<(Entity, &ScreenDiagsOverlay), With<OverlayNodes>>,
+) {
+    if !ui_scale.is_some_and(|ui_scale| ui_scale.is_changed() && !ui_scale.is_added()) {
+        return;
+    }
+    for (entity, overlay) in overlay_query.iter() {
+        if overlay.style.ignore_ui_scale {
+            commands
+                .entity(entity)
+                .despawn_descendants()
+                .remove::<OverlayNodes>();
+        }
+    }
+}
+
+// Builds the UI nodes of all the overlays again when their scale changes
+pub(crate) fn rebuild_on_scale(
+    mut commands: Commands,
+    scale: Res<ScreenDiagsScale>,
+    overlay_query: Query<Entity, With<OverlayNodes>>,
+) {
+    if !scale.is_changed() || scale.is_added() {
+        return;
+    }
+    for entity in overlay_query.iter() {
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .remove::<OverlayNodes>();
+    }
+}
+
+// Builds the UI nodes of the overlays in columns again when the mode changes, as the compact mode
+// is laid out in one line
+#[allow(clippy::type_complexity)]
+pub(crate) fn rebuild_on_mode(
+    mut commands: Commands,
+    mode: Res<ScreenDiagsDisplayMode>,
+    overlay_query: Query<
+        (Entity, &ScreenDiagsOverlay),
+        (With<OverlayNodes>, Without<ScreenDiagsWorldSpace>),
+    >,
+) {
+    if !mode.is_changed() || mode.is_added() {
+        return;
+    }
+    for (entity, overlay) in overlay_query.iter() {
+        if overlay.style.columns {
+            commands
+                .entity(entity)
+                .despawn_descendants()
+                .remove::<OverlayNodes>();
+        }
+    }
+}
+
+// Notes which overlays have their font loaded, so that they are hidden until then. The overlays
+// whose font failed to load are built again with their fallback font, with a warning once for
+// each font, as a missing font otherwise leaves nothing on the screen.
+pub(crate) fn check_overlay_fonts(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    fonts: Res<Assets<Font>>,
+    mut warned: Local<Vec<ScreenDiagsFont>>,
+    mut overlay_query: Query<(Entity, &mut ScreenDiagsOverlay), With<OverlayNodes>>,
+) {
+    for (entity, mut overlay) in overlay_query.iter_mut() {
+        // Handles the app made itself were not loaded by the asset server
+        let (loaded, failed) = match &overlay.style.font {
+            ScreenDiagsFont::Path(path) => {
+                let load_state = asset_server.get_load_state(path.as_str());
+                (
+                    load_state == LoadState::Loaded,
+                    load_state == LoadState::Failed,
+                )
+            }
+            ScreenDiagsFont::Handle(handle) => (
+                fonts.contains(handle),
+                asset_server.get_load_state(handle) == LoadState::Failed,
+            ),
+            _ => (true, false),
+        };
+        if overlay.font_loaded != loaded {
+            overlay.bypass_change_detection().font_loaded = loaded;
+        }
+        if !failed {
+            continue;
+        }
+        let style = &overlay.style;
+        if !warned.contains(&style.font) {
+            warn!(
+                "The diagnostics font {:?} failed to load, so {:?} is used instead. Put the font \
+                in the assets folder, or choose another in the ScreenDiagsStyle.",
+                style.font, style.fallback_font
+            );
+            warned.push(style.font.clone());
+        }
+        if style.fallback_font == style.font {
+            continue;
+        }
+        overlay.style.font = overlay.style.fallback_font.clone();
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .remove::<OverlayNodes>();
+    }
+}
+
+// What the sizes in the style are divided by: the UI scale if the overlay ignores it, so that it
+// cancels out, over the overlay scale
+fn size_divisor(
+    style: &ScreenDiagsStyle,
+    ui_scale: Option<&UiScale>,
+    scale: &ScreenDiagsScale,
+) -> f32 {
+    let ui_scale = match ui_scale {
+        Some(ui_scale) if style.ignore_ui_scale => ui_scale.scale as f32,
+        _ => 1.0,
+    };
+    ui_scale / scale.0
+}
+
+// Divides a size in pixels by the scale
+fn unscaled(value: Val, scale: f32) -> Val {
+    match value {
+        Val::Px(px) => Val::Px(px / scale),
+        _ => value,
+    }
+}
+
+// Spawns an overlay in each new window other than the primary one
+pub(crate) fn spawn_window_overlays(
+    mut commands: Commands,
+    config: Res<ScreenDiagsConfig>,
+    style: Res<ScreenDiagsStyle>,
+    window_query: Query<Entity, (Added<Window>, Without<PrimaryWindow>)>,
+) {
+    for window in window_query.iter() {
+        let overlay = ScreenDiagsOverlay::new(config.clone(), style.clone()).with_window(window);
+        commands.spawn(overlay);
+    }
+}
+
+// The window other than the primary one that the overlay is shown in, if any
+fn other_window(
+    overlay: &ScreenDiagsOverlay,
+    primary_query: &Query<Entity, With<PrimaryWindow>>,
+) -> Option<Entity> {
+    overlay
+        .window
+        .filter(|window| !primary_query.contains(*window))
+}
+
+// Adds the UI nodes to any new overlays
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn build_overlays(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    ui_scale: Option<Res<UiScale>>,
+    overlay_scale: Res<ScreenDiagsScale>,
+    mode: Res<ScreenDiagsDisplayMode>,
+    primary_query: Query<Entity, With<PrimaryWindow>>,
+    overlay_query: Query<
+        (
+            Entity,
+            &ScreenDiagsOverlay,
+            Option<&Transform>,
+            Option<&ScreenDiagsWorldSpace>,
+        ),
+        Without<OverlayNodes>,
+    >,
+    // How many overlays have been built in other windows, to give each a region of its own
+    mut window_overlays: Local<u16>,
+) {
+    for (entity, overlay, transform, world_space) in overlay_query.iter() {
+        let style = &overlay.style;
+        let window = other_window(overlay, &primary_query);
+        // The UI scale does not apply to 2D text
+        let scale = if world_space.is_some() || window.is_some() {
+            1.0 / overlay_scale.0
+        } else {
+            size_divisor(style, ui_scale.as_deref(), &overlay_scale)
+        };
+        let text_style = TextStyle {
+            font: style.font.handle(&asset_server),
+            font_size: style.font_size / scale,
+            color: style.color,
+        };
+        let rows = mode.rows(&overlay.config.rows);
+
+        if world_space.is_some() || window.is_some() {
+            let sections = sections(rows, *mode, None, &text_style);
+            // In another window, the text is far from the rest of the world, and only drawn by
+            // the overlay's own camera
+            let (transform, layers, anchor) = match window {
+                Some(_) => {
+                    *window_overlays = window_overlays.wrapping_add(1);
+                    let region = Vec3::X * WINDOW_REGION * f32::from(*window_overlays);
+                    let layers = RenderLayers::layer(ScreenDiagsOverlay::WINDOW_LAYER);
+                    let (_, anchor) = window_corner(style, Vec2::ZERO);
+                    (Transform::from_translation(region), layers, anchor)
+                }
+                None => (
+                    transform.copied().unwrap_or_default(),
+                    RenderLayers::default(),
+                    Anchor::default(),
+                ),
+            };
+            let camera_order = WINDOW_CAMERA_ORDER + *window_overlays as isize;
+            commands
+                .entity(entity)
+                .insert((
+                    OverlayNodes,
+                    GlobalTransform::default(),
+                    VisibilityBundle::default(),
+                    transform,
+                ))
+                .with_children(|parent| {
+                    if let Some(window) = window {
+                        parent.spawn((
+                            Camera2dBundle {
+                                camera: Camera {
+                                    target: RenderTarget::Window(WindowRef::Entity(window)),
+                                    order: camera_order,
+                                    ..Default::default()
+                                },
+                                camera_2d: Camera2d {
+                                    clear_color: ClearColorConfig::None,
+                                },
+                                ..Default::default()
+                            },
+                            UiCameraConfig { show_ui: false },
+                            layers,
+                        ));
+                    }
+                    let shadow_sections = shadow_sections(&sections, style.shadow);
+                    let mut text = parent.spawn((
+                        Text2dBundle {
+                            text: Text::from_sections(sections),
+                            text_anchor: anchor.clone(),
+                            ..Default::default()
+                        },
+                        ScreenDiagsText,
+                        layers,
+                    ));
+                    // Children are drawn in front of the text unless they are further back
+                    let Some(shadow) = style.shadow else {
+                        return;
+                    };
+                    let mut shadows = Vec::new();
+                    text.with_children(|parent| {
+                        for offset in shadow.offsets() {
+                            let shadow = parent.spawn((
+                                Text2dBundle {
+                                    text: Text::from_sections(shadow_sections.clone()),
+                                    transform: Transform::from_xyz(offset.x, -offset.y, -0.001),
+                                    text_anchor: anchor.clone(),
+                                    ..Default::default()
+                                },
+                                TextShadow,
+                                layers,
+                            ));
+                            shadows.push(shadow.id());
+                        }
+                    });
+                    text.insert(Shadowed(shadows));
+                });
+            continue;
+        }
+
+        let mut node_style = Style::default();
+        style
+            .position
+            .apply(unscaled(style.margin, scale), &mut node_style);
+        let mut background_color = BackgroundColor(Color::NONE);
+        if let Some(panel) = style.panel {
+            let padding = panel.padding;
+            node_style.padding = UiRect::new(
+                unscaled(padding.left, scale),
+                unscaled(padding.right, scale),
+                unscaled(padding.top, scale),
+                unscaled(padding.bottom, scale),
+            );
+            background_color = panel.color.into();
+        }
+
+        let padding = node_style.padding;
+        let mut overlay_nodes = commands.entity(entity);
+        overlay_nodes.insert((
+            NodeBundle {
+                style: node_style,
+                background_color,
+                focus_policy: style.focus_policy,
+                z_index: style.z_index,
+                ..Default::default()
+            },
+            OverlayNodes,
+        ));
+        overlay_nodes.with_children(|parent| {
+            if style.draggable {
+                spawn_drag_handles(parent, padding);
+            }
+            if style.columns && rows.len() > 1 && *mode != ScreenDiagsDisplayMode::Compact {
+                let labels = sections(rows, *mode, Some(OverlayColumn::Labels), &text_style);
+                let values = sections(rows, *mode, Some(OverlayColumn::Values), &text_style);
+                let gap = UiRect::left(Val::Px(text_style.font_size / 2.0));
+                let labels_column = Some(OverlayColumn::Labels);
+                spawn_text(
+                    parent,
+                    labels,
+                    labels_column,
+                    UiRect::default(),
+                    style,
+                    scale,
+                );
+                let values_column = Some(OverlayColumn::Values);
+                spawn_text(parent, values, values_column, gap, style, scale);
+            } else {
+                let sections = sections(rows, *mode, None, &text_style);
+                spawn_text(parent, sections, None, UiRect::default(), style, scale);
+            }
+            if let Some(graph) = &overlay.graph {
+                graph::spawn_graph(parent, graph);
+            }
+            if let Some(histogram) = &overlay.histogram {
+                histogram::spawn_histogram(parent, histogram, &text_style, scale);
+            }
+            if let Some(budget_bar) = &overlay.budget_bar {
+                budget::spawn_budget_bar(parent, budget_bar);
+            }
+        });
+    }
+}
+
+// Adds the nodes the overlay is dragged by: the border of the panel, or the whole overlay if the
+// panel has no padding. They capture the clicks, which the rest of the overlay may let through.
+fn spawn_drag_handles(parent: &mut ChildBuilder, padding: UiRect) {
+    let edge = Val::Px(0.0);
+    let sides = [padding.left, padding.right, padding.top, padding.bottom];
+    // The offsets from the edges and the size of each handle
+    let handles = if sides
+        .iter()
+        .all(|side| matches!(side, Val::Px(px) if *px <= 0.0))
+    {
+        vec![(UiRect::all(edge), Val::Auto, Val::Auto)]
+    } else {
+        // The top, bottom, left and right strips
+        vec![
+            (
+                UiRect::new(edge, edge, edge, Val::Auto),
+                Val::Auto,
+                padding.top,
+            ),
+            (
+                UiRect::new(edge, edge, Val::Auto, edge),
+                Val::Auto,
+                padding.bottom,
+            ),
+            (
+                UiRect::new(edge, Val::Auto, edge, edge),
+                padding.left,
+                Val::Auto,
+            ),
+            (
+                UiRect::new(Val::Auto, edge, edge, edge),
+                padding.right,
+                Val::Auto,
+            ),
+        ]
+    };
+    for (offsets, width, height) in handles {
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: offsets.left,
+                    right: offsets.right,
+                    top: offsets.top,
+                    bottom: offsets.bottom,
+                    width,
+                    height,
+                    ..Default::default()
+                },
+                focus_policy: FocusPolicy::Block,
+                ..Default::default()
+            },
+            Interaction::default(),
+            DragHandle,
+        ));
+    }
+}
+
+// Spawns a text of an overlay on the screen. With a shadow, the text and its shadows are put in a
+// node of their own, as children are drawn in front of their parents.
+fn spawn_text(
+    parent: &mut ChildBuilder,
+    sections: Vec<TextSection>,
+    column: Option<OverlayColumn>,
+    margin: UiRect,
+    style: &ScreenDiagsStyle,
+    scale: f32,
+) {
+    let node_style = Style {
+        margin,
+        ..Default::default()
+    };
+    let Some(shadow) = style.shadow else {
+        let mut text = parent.spawn((
+            TextBundle::from_sections(sections).with_style(node_style),
+            ScreenDiagsText,
+        ));
+        if let Some(column) = column {
+            text.insert(column);
+        }
+        return;
+    };
+
+    parent
+        .spawn(NodeBundle {
+            style: node_style,
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            let shadows = shadow
+                .offsets()
+                .into_iter()
+                .map(|offset| {
+                    let shadow_style = Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(offset.x / scale),
+                        top: Val::Px(offset.y / scale),
+                        ..Default::default()
+                    };
+                    let sections = shadow_sections(&sections, Some(shadow));
+                    parent
+                        .spawn((
+                            TextBundle::from_sections(sections).with_style(shadow_style),
+                            TextShadow,
+                        ))
+                        .id()
+                })
+                .collect();
+            let mut text = parent.spawn((
+                TextBundle::from_sections(sections),
+                ScreenDiagsText,
+                Shadowed(shadows),
+            ));
+            if let Some(column) = column {
+                text.insert(column);
+            }
+        });
+}
+
+// The sections of a text in the colour of the shadow, if there is one
+fn shadow_sections(
+    sections: &[TextSection],
+    shadow: Option<ScreenDiagsShadow>,
+) -> Vec<TextSection> {
+    let mut sections = sections.to_vec();
+    if let Some(shadow) = shadow {
+        for section in &mut sections {
+            section.style.color = shadow.color();
+        }
+    }
+    sections
+}
+
+// The sections of a new text, one per row, with no readings yet
+fn sections(
+    rows: &[ScreenDiagsRow],
+    mode: ScreenDiagsDisplayMode,
+    column: Option<OverlayColumn>,
+    text_style: &TextStyle,
+) -> Vec<TextSection> {
+    let readings = ScreenDiagsReadings::default();
+    let mut sections = rows
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let mut value = String::new();
+            write_section(&mut value, index, mode, column, row, &readings);
+            TextSection::new(value, text_style.clone())
+        })
+        .collect::<Vec<_>>();
+    if sections.is_empty() {
+        sections.push(TextSection::new("", text_style.clone()));
+    }
+    sections
+}
+
+// Fades the overlays in and out, and hides them, panel and all, once they have faded out while
+// the display is disabled or off, or they are hidden
+pub(crate) fn update_overlay_visibility(
+    time: Res<Time>,
+    state: Res<ScreenDiagsState>,
+    mode: Res<ScreenDiagsDisplayMode>,
+    auto_hide: Option<Res<ScreenDiagsAutoHide>>,
+    mut overlay_query: Query<(&mut ScreenDiagsOverlay, &mut Visibility)>,
+) {
+    let shown = state.enabled() && *mode != ScreenDiagsDisplayMode::Off;
+    let asleep = auto_hide.as_ref().filter(|auto_hide| auto_hide.is_asleep());
+    for (mut overlay, mut visibility) in overlay_query.iter_mut() {
+        // The opacity is internal, so leave change detection for the user's changes
+        let overlay = overlay.bypass_change_detection();
+        let awake = shown && !overlay.hidden;
+        let target = if awake && overlay.font_loaded && asleep.is_none() {
+            1.0
+        } else {
+            0.0
+        };
+        // Falling asleep fades at the pace of the auto-hide
+        let fade = match &asleep {
+            Some(auto_hide) if awake => auto_hide.fade,
+            _ => overlay.style.fade,
+        };
+        // Until the font is loaded there is nothing to fade out
+        let fade = if overlay.font_loaded {
+            fade.as_secs_f32()
+        } else {
+            0.0
+        };
+        let opacity = if fade > 0.0 {
+            let step = time.delta_seconds() / fade;
+            if target > overlay.opacity {
+                (overlay.opacity + step).min(target)
+            } else {
+                (overlay.opacity - step).max(target)
+            }
+        } else {
+            target
+        };
+        overlay.faded = opacity != overlay.opacity;
+        overlay.opacity = opacity;
+
+        visibility.set_if_neq(if opacity > 0.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+// Where the text of an overlay in another window goes relative to the centre of a window of this
+// size, and the corner of the text placed there. Positions that are not a corner are taken as the
+// top left.
+fn window_corner(style: &ScreenDiagsStyle, size: Vec2) -> (Vec2, Anchor) {
+    let px = |value| match value {
+        Val::Px(px) => px,
+        _ => 0.0,
+    };
+    let margin = px(style.margin);
+    let (left, top, right, bottom) = (
+        margin - size.x / 2.0,
+        size.y / 2.0 - margin,
+        size.x / 2.0 - margin,
+        margin - size.y / 2.0,
+    );
+    match style.position {
+        ScreenDiagsPosition::TopRight => (Vec2::new(right, top), Anchor::TopRight),
+        ScreenDiagsPosition::BottomLeft => (Vec2::new(left, bottom), Anchor::BottomLeft),
+        ScreenDiagsPosition::BottomRight => (Vec2::new(right, bottom), Anchor::BottomRight),
+        ScreenDiagsPosition::Absolute(rect) => {
+            let corner = Vec2::new(px(rect.left) - size.x / 2.0, size.y / 2.0 - px(rect.top));
+            (corner, Anchor::TopLeft)
+        }
+        _ => (Vec2::new(left, top), Anchor::TopLeft),
+    }
+}
+
+// Keeps the overlays in other windows in their corner as the windows are resized, and despawns
+// them when their window closes
+pub(crate) fn place_window_overlays(
+    mut commands: Commands,
+    window_query: Query<&Window>,
+    primary_query: Query<Entity, With<PrimaryWindow>>,
+    overlay_query: Query<(Entity, &ScreenDiagsOverlay, &Children), With<OverlayNodes>>,
+    mut text_query: Query<&mut Transform, With<ScreenDiagsText>>,
+) {
+    for (entity, overlay, children) in overlay_query.iter() {
+        let Some(window) = other_window(overlay, &primary_query) else {
+            continue;
+        };
+        let Ok(window) = window_query.get(window) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        let size = Vec2::new(window.width(), window.height());
+        let (corner, _) = window_corner(&overlay.style, size);
+        let mut texts = text_query.iter_many_mut(children.iter());
+        while let Some(mut transform) = texts.fetch_next() {
+            if transform.translation.truncate() != corner {
+                transform.translation = corner.extend(transform.translation.z);
+            }
+        }
+    }
+}
+
+// Moves the overlays that follow a camera into its viewport, as the UI is laid out for the window
+#[allow(clippy::type_complexity)]
+pub(crate) fn place_overlays(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    ui_scale: Option<Res<UiScale>>,
+    overlay_scale: Res<ScreenDiagsScale>,
+    camera_query: Query<(Entity, &Camera, Option<&ScreenDiagsCamera>)>,
+    mut overlay_query: Query<
+        (&ScreenDiagsOverlay, &mut Style),
+        (With<OverlayNodes>, Without<ScreenDiagsWorldSpace>),
+    >,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    let marked_camera = camera_query
+        .iter()
+        .find(|(_, _, marker)| marker.is_some())
+        .map(|(entity, ..)| entity);
+
+    for (overlay, mut node_style) in overlay_query.iter_mut() {
+        // Overlays in the app's own UI are placed by their parent
+        if overlay.style.position == ScreenDiagsPosition::Inline {
+            continue;
+        }
+        let Some(camera) = overlay.camera.or(marked_camera) else {
+            continue;
+        };
+        let Some(viewport) = camera_query
+            .get(camera)
+            .ok()
+            .and_then(|(_, camera, _)| camera.logical_viewport_rect())
+        else {
+            continue;
+        };
+
+        // The UI is laid out in logical pixels divided by the UI scale
+        let layout_scale = ui_scale
+            .as_ref()
+            .map_or(1.0, |ui_scale| ui_scale.scale as f32);
+        let viewport = Rect::from_corners(viewport.min / layout_scale, viewport.max / layout_scale);
+        let window_size = Vec2::new(window.width(), window.height()) / layout_scale;
+
+        let margin = unscaled(
+            overlay.style.margin,
+            size_divisor(&overlay.style, ui_scale.as_deref(), &overlay_scale),
+        );
+        let (top, right, bottom, left) = overlay.style.position.offsets(margin);
+        let size = viewport.size();
+        let top = offset(top, viewport.min.y, size.y);
+        let right = offset(right, window_size.x - viewport.max.x, size.x);
+        let bottom = offset(bottom, window_size.y - viewport.max.y, size.y);
+        let left = offset(left, viewport.min.x, size.x);
+        if (
+            node_style.top,
+            node_style.right,
+            node_style.bottom,
+            node_style.left,
+        ) != (top, right, bottom, left)
+        {
+            node_style.top = top;
+            node_style.right = right;
+            node_style.bottom = bottom;
+            node_style.left = left;
+        }
+    }
+}
+
+// Whether any overlay can be dragged, so the mouse needs watching
+pub(crate) fn any_draggable(handle_query: Query<(), With<DragHandle>>) -> bool {
+    !handle_query.is_empty()
+}
+
+// Moves the draggable overlays with the mouse, from where they were grabbed by a handle
+#[allow(clippy::type_complexity)]
+pub(crate) fn drag_overlays(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    ui_scale: Option<Res<UiScale>>,
+    mouse: Res<Input<MouseButton>>,
+    mut primary_style: ResMut<ScreenDiagsStyle>,
+    handle_query: Query<(&Interaction, &Parent), With<DragHandle>>,
+    mut overlay_query: Query<
+        (
+            &mut ScreenDiagsOverlay,
+            &Node,
+            &GlobalTransform,
+            &mut Style,
+            Option<&PrimaryOverlay>,
+        ),
+        Without<ScreenDiagsWorldSpace>,
+    >,
+    // The overlay being dragged, and where it was grabbed relative to its top left corner
+    mut dragged: Local<Option<(Entity, Vec2)>>,
+) {
+    if !mouse.pressed(MouseButton::Left) {
+        *dragged = None;
+        return;
+    }
+    let Some(cursor) = window_query
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+    else {
+        return;
+    };
+
+    if mouse.just_pressed(MouseButton::Left) {
+        *dragged = handle_query
+            .iter()
+            .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+            .find_map(|(_, parent)| {
+                let (overlay, node, transform, ..) = overlay_query.get(parent.get()).ok()?;
+                let top_left = transform.translation().truncate() - node.size() / 2.0;
+                overlay
+                    .style
+                    .draggable
+                    .then_some((parent.get(), cursor - top_left))
+            });
+    }
+    let Some((entity, grab)) = *dragged else {
+        return;
+    };
+    let Ok((mut overlay, .., mut node_style, primary)) = overlay_query.get_mut(entity) else {
+        *dragged = None;
+        return;
+    };
+
+    // The UI is laid out in logical pixels divided by the UI scale
+    let layout_scale = ui_scale
+        .as_ref()
+        .map_or(1.0, |ui_scale| ui_scale.scale as f32);
+    let top_left = (cursor - grab) / layout_scale;
+    let position = ScreenDiagsPosition::Absolute(UiRect {
+        left: Val::Px(top_left.x),
+        top: Val::Px(top_left.y),
+        ..UiRect::all(Val::Auto)
+    });
+    if overlay.style.position == position {
+        return;
+    }
+    position.apply(Val::Auto, &mut node_style);
+    // Changing the style would build the nodes again, and lose the drag
+    overlay.bypass_change_detection().style.position = position;
+    if primary.is_some() {
+        primary_style.bypass_change_detection().position = position;
+    }
+}
+
+// Moves an offset from the edge of the viewport to the edge of the window. Percentages are of the
+// viewport rather than the window.
+fn offset(value: Val, edge: f32, size: f32) -> Val {
+    match value {
+        Val::Px(px) => Val::Px(edge + px),
+        Val::Percent(percent) => Val::Px(edge + size * percent / 100.0),
+        _ => value,
+    }
+}
+
+// Ticks the timer of each overlay, and works out whether it updates this frame
+pub(crate) fn tick_overlays(
+    time: Res<Time>,
+    state: Res<ScreenDiagsState>,
+    mode: Res<ScreenDiagsDisplayMode>,
+    rows_config: Res<ScreenDiagsRowsConfig>,
+    mut overlay_query: Query<&mut ScreenDiagsOverlay>,
+) {
+    for mut overlay in overlay_query.iter_mut() {
+        // Changes made here are internal, so leave change detection for the user's changes
+        let forced = state.update_now
+            || mode.is_changed()
+            || rows_config.is_changed()
+            || overlay.is_changed();
+        let overlay = overlay.bypass_change_detection();
+
+        if overlay.timer.duration() != overlay.config.interval {
+            overlay.timer.set_duration(overlay.config.interval);
+            overlay.timer.reset();
+        }
+        let popped = overlay.timer.tick(time.delta()).just_finished();
+        overlay.updated = state.enabled()
+            && *mode != ScreenDiagsDisplayMode::Off
+            && !overlay.hidden
+            && (popped || forced);
+    }
+}
+
+// Takes new readings and rewrites the text of each overlay that updates this frame
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn update_overlays(
+    mut sources: MetricSources,
+    time: Res<Time>,
+    mode: Res<ScreenDiagsDisplayMode>,
+    rows_config: Res<ScreenDiagsRowsConfig>,
+    mut overlay_query: Query<(
+        &mut ScreenDiagsOverlay,
+        &Children,
+        Option<&mut BackgroundColor>,
+    )>,
+    children_query: Query<&Children>,
+    mut text_query: Query<
+        (&mut Text, Option<&OverlayColumn>, Option<&Shadowed>),
+        With<ScreenDiagsText>,
+    >,
+    mut shadow_query: Query<&mut Text, (With<TextShadow>, Without<ScreenDiagsText>)>,
+) {
+    for (mut overlay, children, panel_color) in overlay_query.iter_mut() {
+        // While fading out or blinking, the text is not updated but its colours are
+        let blinking = overlay.opacity > 0.0 && overlay.style.is_warning(&overlay.readings);
+        if !overlay.updated && !overlay.faded && !blinking {
+            continue;
+        }
+
+        let ScreenDiagsOverlay {
+            config,
+            style,
+            readings,
+            line,
+            updated,
+            opacity,
+            faded,
+            ..
+        } = overlay.bypass_change_detection();
+        let rows = mode.rows(&config.rows);
+        if *updated {
+            for row in rows.iter().filter(|row| rows_config.is_shown(row.metric)) {
+                readings.sample(row.metric, &mut sources);
+            }
+        }
+        if *faded {
+            if let (Some(panel), Some(mut panel_color)) = (style.panel, panel_color) {
+                panel_color.0 = faded_color(panel.color, *opacity);
+            }
+        }
+
+        let readings = &*readings;
+        let elapsed = time.raw_elapsed_seconds_f64();
+        let row_color = |metric| {
+            if !rows_config.is_shown(metric) {
+                return None;
+            }
+            let color = style
+                .row_color(metric, readings, elapsed)
+                .unwrap_or(style.color);
+            Some(faded_color(color, *opacity))
+        };
+        let shadow_color = style
+            .shadow
+            .map(|shadow| faded_color(shadow.color(), *opacity));
+        // Texts with shadows are in a node of their own
+        let texts = children.iter().flat_map(|child| {
+            let grandchildren = children_query.get(*child).into_iter();
+            std::iter::once(child).chain(grandchildren.flat_map(|children| children.iter()))
+        });
+        for child in texts {
+            if let Ok((mut text, column, shadowed)) = text_query.get_mut(*child) {
+                let column = column.copied();
+                write_text(&mut text, *mode, column, rows, readings, line, row_color);
+                let (Some(shadowed), Some(color)) = (shadowed, shadow_color) else {
+                    continue;
+                };
+                for shadow in &shadowed.0 {
+                    if let Ok(mut shadow) = shadow_query.get_mut(*shadow) {
+                        copy_text(&text, &mut shadow, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// A colour with its alpha scaled by the opacity of the overlay
+pub(crate) fn faded_color(color: Color, opacity: f32) -> Color {
+    color.with_a(color.a() * opacity)
+}
+
+// Writes the rows, or a column of them, into the text, one section per shown row, in the colours
+// of their metrics, which are None for hidden rows. Sections are only changed when their text or
+// colour differs, as a change to the text makes Bevy lay it out again.
+fn write_text(
+    text: &mut Mut<Text>,
+    mode: ScreenDiagsDisplayMode,
+    column: Option<OverlayColumn>,
+    rows: &[ScreenDiagsRow],
+    readings: &ScreenDiagsReadings,
+    line: &mut String,
+    row_color: impl Fn(ScreenDiagsMetric) -> Option<Color>,
+) {
+    let Some(first) = text.sections.first() else {
+        return;
+    };
+    let row_color = &row_color;
+    let shown = move || {
+        rows.iter()
+            .filter_map(move |row| Some((row, row_color(row.metric)?)))
+    };
+    // The rows may have changed or been hidden since the last time
+    let sections = shown().count().max(1);
+    if text.sections.len() != sections {
+        let section = TextSection::new("", first.style.clone());
+        text.sections.resize(sections, section);
+    }
+
+    let mut shown_rows = shown();
+    for index in 0..sections {
+        line.clear();
+        let section = &text.sections[index];
+        let mut color = section.style.color;
+        if let Some((row, row_color)) = shown_rows.next() {
+            write_section(line, index, mode, column, row, readings);
+            color = row_color;
+        }
+        if section.value != *line || section.style.color != color {
+            let section = &mut text.sections[index];
+            section.value.clone_from(line);
+            section.style.color = color;
+        }
+    }
+}
+
+// Copies the values of the sections of a text into its shadow, which has a colour of its own
+fn copy_text(text: &Text, shadow: &mut Mut<Text>, color: Color) {
+    let Some(first) = shadow.sections.first() else {
+        return;
+    };
+    if shadow.sections.len() != text.sections.len() {
+        let section = TextSection::new("", first.style.clone());
+        shadow.sections.resize(text.sections.len(), section);
+    }
+    for (index, section) in text.sections.iter().enumerate() {
+        let shadow_section = &shadow.sections[index];
+        if shadow_section.value != section.value || shadow_section.style.color != color {
+            let shadow_section = &mut shadow.sections[index];
+            shadow_section.value.clone_from(&section.value);
+            shadow_section.style.color = color;
+        }
+    }
+}
+
+// Writes a row's section of the text, or of a column, after the separator from the row before
+fn write_section(
+    value: &mut String,
+    index: usize,
+    mode: ScreenDiagsDisplayMode,
+    column: Option<OverlayColumn>,
+    row: &ScreenDiagsRow,
+    readings: &ScreenDiagsReadings,
+) {
+    if index > 0 {
+        value.push_str(mode.separator());
+    }
+    match column {
+        None => write_row(value, 0, row, readings),
+        Some(OverlayColumn::Labels) => write_label(value, row, readings),
+        Some(OverlayColumn::Values) => write_value(value, row, readings),
+    }
+}
+
+// Writes a row's line of text
+pub(crate) fn write_row(
+    value: &mut String,
+    index: usize,
+    row: &ScreenDiagsRow,
+    readings: &ScreenDiagsReadings,
+) {
+    if index > 0 {
+        value.push('\n');
+    }
+    write_label(value, row, readings);
+    value.push(' ');
+    write_value(value, row, readings);
+}
+
+// Writes a row's label, with its colon
+fn write_label(value: &mut String, row: &ScreenDiagsRow, readings: &ScreenDiagsReadings) {
+    let label = row
+        .label
+        .as_deref()
+        .unwrap_or_else(|| readings.label(row.metric));
+    value.push_str(label);
+    value.push(':');
+}
+
+// How a value is written
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ValueFormat {
+    Decimals(usize),
+    // A whole number, with thousands separators
+    Count,
+}
+
+impl ValueFormat {
+    // The format suiting a value with this suffix
+    fn suiting(suffix: &str, value: f64) -> Self {
+        match suffix {
+            "s" | "ms" | "us" | "µs" | "ns" => Self::Decimals(2),
+            "%" | "KB" | "MB" | "GB" | "KiB" | "MiB" | "GiB" => Self::Decimals(1),
+            // Without a unit it is most likely a count, unless it has a fraction worth showing
+            "" if value.fract() == 0.0 || value.abs() >= 1000.0 => Self::Count,
+            _ => Self::Decimals(2),
+        }
+    }
+
+    fn precision(self) -> usize {
+        match self {
+            Self::Decimals(precision) => precision,
+            Self::Count => 0,
+        }
+    }
+}
+
+// Writes a whole number with commas between the thousands, in place so as not to allocate
+fn write_count(value: &mut String, count: f64) {
+    let count = count.round();
+    if count < 0.0 {
+        value.push('-');
+    }
+    let start = value.len();
+    write!(value, "{:.0}", count.abs()).unwrap();
+    if !count.is_finite() {
+        return;
+    }
+    let mut end = value.len();
+    while end - start > 3 {
+        end -= 3;
+        value.insert(end, ',');
+    }
+}
+
+// Writes a row's values, with their suffix
+fn write_value(value: &mut String, row: &ScreenDiagsRow, readings: &ScreenDiagsReadings) {
+    if let Some(text) = readings.text(row.metric) {
+        value.push_str(text);
+        return;
+    }
+    let values = readings.values(row.metric);
+    if values.is_empty() {
+        value.push_str(STRING_INITIAL);
+        return;
+    }
+    let baseline = readings.baseline(row.metric);
+    for (index, reading) in values.iter().enumerate() {
+        if index > 0 {
+            value.push_str(" / ");
+        }
+        let format = if row.auto_format {
+            ValueFormat::suiting(readings.suffix(row.metric), *reading)
+        } else {
+            ValueFormat::Decimals(row.precision)
+        };
+        match (row.formatter, format) {
+            (Some(formatter), _) => formatter(value, *reading).unwrap(),
+            (None, ValueFormat::Decimals(precision)) => {
+                write!(value, "{:.*}", precision, reading).unwrap()
+            }
+            (None, ValueFormat::Count) => write_count(value, *reading),
+        }
+        if let Some(baseline) = baseline.get(index) {
+            write!(value, " ({:+.*})", format.precision(), reading - baseline).unwrap();
+        }
+    }
+    // A formatter writes its own suffix
+    let suffix = row
+        .suffix
+        .as_deref()
+        .unwrap_or_else(|| readings.suffix(row.metric));
+    if row.formatter.is_none() && !suffix.is_empty() {
+        value.push(' ');
+        value.push_str(suffix);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_counts_with_thousands_separators() {
+        let cases = [
+            (0.0, "0"),
+            (999.0, "999"),
+            (1000.0, "1,000"),
+            (-1234.0, "-1,234"),
+            (-999.0, "-999"),
+            (999.6, "1,000"),
+            (1_234_567_890.0, "1,234,567,890"),
+        ];
+        for (count, expected) in cases {
+            let mut value = String::from("Entities: ");
+            write_count(&mut value, count);
+            assert_eq!(value, format!("Entities: {}", expected), "{}", count);
+        }
+    }
+
+    #[test]
+    fn suits_the_format_to_the_suffix() {
+        let cases = [
+            ("ms", 16.667, ValueFormat::Decimals(2)),
+            ("s", 1.5, ValueFormat::Decimals(2)),
+            ("%", 45.25, ValueFormat::Decimals(1)),
+            ("MiB", 512.0, ValueFormat::Decimals(1)),
+            ("", 0.0, ValueFormat::Count),
+            ("", 999.0, ValueFormat::Count),
+            ("", 1000.5, ValueFormat::Count),
+            ("", -2.0, ValueFormat::Count),
+            ("", 0.5, ValueFormat::Decimals(2)),
+            ("widgets", 3.0, ValueFormat::Decimals(2)),
+        ];
+        for (suffix, value, expected) in cases {
+            assert_eq!(
+                ValueFormat::suiting(suffix, value),
+                expected,
+                "{} {}",
+                value,
+                suffix
+            );
+        }
+    }
+}
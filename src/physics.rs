@@ -0,0 +1,66 @@
+//! Readings of the Rapier physics, timed around its simulation step.
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    utils::Instant,
+};
+use bevy_rapier3d::{
+    plugin::PhysicsSet,
+    prelude::{Collider, RigidBody},
+};
+
+/// The time taken by the physics step, in milliseconds.
+pub(crate) const PHYSICS_STEP: DiagnosticId =
+    DiagnosticId::from_u128(0x3e91_7b2c_d54a_4f60_8c13_a6e2_59d0_b7f4);
+
+/// The number of rigid bodies.
+pub(crate) const RIGID_BODIES: DiagnosticId =
+    DiagnosticId::from_u128(0xa7c4_02e9_6b3f_48d1_95e0_1f7a_c8b6_3d52);
+
+/// The number of colliders.
+pub(crate) const COLLIDERS: DiagnosticId =
+    DiagnosticId::from_u128(0x5d08_f3a6_19c7_4e2b_b4f9_e06d_72a1_8c3e);
+
+// The frames the step time is averaged over
+const STEP_HISTORY: usize = 20;
+
+// When the physics step of this frame started
+#[derive(Resource, Default)]
+struct StepStart(Option<Instant>);
+
+pub(crate) fn register(app: &mut App) {
+    app.register_diagnostic(
+        Diagnostic::new(PHYSICS_STEP, "Physics step", STEP_HISTORY).with_suffix("ms"),
+    )
+    .register_diagnostic(Diagnostic::new(RIGID_BODIES, "Rigid bodies", 1))
+    .register_diagnostic(Diagnostic::new(COLLIDERS, "Colliders", 1))
+    .init_resource::<StepStart>()
+    .add_systems(
+        PostUpdate,
+        (
+            start_step.before(PhysicsSet::StepSimulation),
+            end_step.after(PhysicsSet::StepSimulation),
+        ),
+    )
+    .add_systems(Last, count_bodies);
+}
+
+fn start_step(mut start: ResMut<StepStart>) {
+    start.0 = Some(Instant::now());
+}
+
+fn end_step(mut start: ResMut<StepStart>, mut diagnostics: Diagnostics) {
+    if let Some(start) = start.0.take() {
+        diagnostics.add_measurement(PHYSICS_STEP, || start.elapsed().as_secs_f64() * 1000.0);
+    }
+}
+
+fn count_bodies(
+    body_query: Query<(), With<RigidBody>>,
+    collider_query: Query<(), With<Collider>>,
+    mut diagnostics: Diagnostics,
+) {
+    diagnostics.add_measurement(RIGID_BODIES, || body_query.iter().count() as f64);
+    diagnostics.add_measurement(COLLIDERS, || collider_query.iter().count() as f64);
+}
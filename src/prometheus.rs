@@ -0,0 +1,181 @@
+//! A Prometheus endpoint, so that playtests can be scraped while they run.
+
+use std::{
+    fmt::Write as _,
+    io::{BufRead, BufReader, Read, Write as _},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use bevy::{
+    diagnostic::{DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+    utils::Duration,
+};
+
+use crate::{
+    extract_fps, is_disabled, ScreenDiagsConfig, ScreenDiagsCorePlugin, ScreenDiagsRows,
+    ScreenDiagsSet, ScreenDiagsState,
+};
+
+/// A plugin that serves the diagnostics at `/metrics` over HTTP, in the Prometheus text format.
+///
+/// The FPS, frame time and entity count are served as gauges, along with any
+/// [custom rows](ScreenDiagsRows) whose text is a number. The numbers are refreshed at the interval
+/// of the [state](ScreenDiagsState) timer, and custom rows when the display updates.
+pub struct ScreenDiagsPrometheusPlugin {
+    address: SocketAddr,
+}
+
+impl ScreenDiagsPrometheusPlugin {
+    /// Serves the metrics at this address.
+    pub fn new(address: impl Into<SocketAddr>) -> Self {
+        Self {
+            address: address.into(),
+        }
+    }
+}
+
+impl Default for ScreenDiagsPrometheusPlugin {
+    fn default() -> Self {
+        Self::new(([127, 0, 0, 1], 9464))
+    }
+}
+
+impl Plugin for ScreenDiagsPrometheusPlugin {
+    fn build(&self, app: &mut App) {
+        if is_disabled(app) {
+            return;
+        }
+        if !app.is_plugin_added::<ScreenDiagsCorePlugin>() {
+            app.add_plugins(ScreenDiagsCorePlugin);
+        }
+
+        let metrics = Arc::new(Mutex::new(String::new()));
+        match TcpListener::bind(self.address) {
+            Ok(listener) => {
+                let served = metrics.clone();
+                thread::spawn(move || serve(listener, &served));
+            }
+            Err(error) => {
+                error!("Failed to serve the metrics at {}: {}", self.address, error);
+            }
+        }
+
+        app.insert_resource(PrometheusMetrics(metrics))
+            .add_systems(Update, write_metrics.in_set(ScreenDiagsSet::Display));
+    }
+}
+
+// How long a client has to send its request or take the answer, as one that stalls holds up the
+// others
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+// The longest request line read, which is plenty for `GET /metrics HTTP/1.1`
+const MAX_REQUEST_LINE: u64 = 1024;
+
+// The latest metrics, shared with the server thread
+#[derive(Resource)]
+struct PrometheusMetrics(Arc<Mutex<String>>);
+
+// Answers each request in turn, as scrapes are few and small
+fn serve(listener: TcpListener, metrics: &Mutex<String>) {
+    for stream in listener.incoming().flatten() {
+        if let Err(error) = respond(stream, metrics) {
+            warn!("Failed to answer a metrics request: {}", error);
+        }
+    }
+}
+
+fn respond(mut stream: TcpStream, metrics: &Mutex<String>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST_LINE)).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+
+    if path == "/metrics" {
+        let body = metrics.lock().unwrap().clone();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        write!(
+            stream,
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        )
+    }
+}
+
+// Writes the metrics in the text format each time the readings are due
+fn write_metrics(
+    state: Res<ScreenDiagsState>,
+    diagnostics: Res<DiagnosticsStore>,
+    config: Res<ScreenDiagsConfig>,
+    custom_rows: Res<ScreenDiagsRows>,
+    metrics: Res<PrometheusMetrics>,
+) {
+    if !state.due {
+        return;
+    }
+
+    // Written in place, to save allocating a new string each time
+    let mut guard = metrics.0.lock().unwrap();
+    let text = &mut *guard;
+    text.clear();
+    let mut gauge = |name: &str, help: &str, value: Option<f64>| {
+        if let Some(value) = value {
+            writeln!(text, "# HELP {} {}", name, help).unwrap();
+            writeln!(text, "# TYPE {} gauge", name).unwrap();
+            writeln!(text, "{} {}", name, value).unwrap();
+        }
+    };
+    gauge(
+        "screen_diags_fps",
+        "The frames per second.",
+        extract_fps(&diagnostics, config.smoothing),
+    );
+    gauge(
+        "screen_diags_frame_time_seconds",
+        "The time taken per frame.",
+        diagnostics
+            .get(FrameTimeDiagnosticsPlugin::FRAME_TIME)
+            .and_then(|frame_time| config.smoothing.smooth(frame_time))
+            .map(|ms| ms / 1000.0),
+    );
+    gauge(
+        "screen_diags_entity_count",
+        "The number of entities in the world.",
+        diagnostics
+            .get(EntityCountDiagnosticsPlugin::ENTITY_COUNT)
+            .and_then(|count| count.value()),
+    );
+
+    let mut wrote_custom_help = false;
+    for (name, row_text) in custom_rows.iter() {
+        let Ok(value) = row_text.trim().parse::<f64>() else {
+            continue;
+        };
+        if !wrote_custom_help {
+            writeln!(
+                text,
+                "# HELP screen_diags_custom The custom rows with numbers."
+            )
+            .unwrap();
+            writeln!(text, "# TYPE screen_diags_custom gauge").unwrap();
+            wrote_custom_help = true;
+        }
+        text.push_str("screen_diags_custom{name=\"");
+        for c in name.chars() {
+            if matches!(c, '\\' | '"') {
+                text.push('\\');
+            }
+            text.push(c);
+        }
+        writeln!(text, "\"}} {}", value).unwrap();
+    }
+}
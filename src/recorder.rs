@@ -0,0 +1,288 @@
+//! Recording the frame times, to be written to a file for later study.
+
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use bevy::{app::AppExit, prelude::*};
+
+use crate::history::percentile;
+
+/// The recorder resource. While it exists, the time and length of every frame are kept, and
+/// written to the file at its path when a [flush event](ScreenDiagsFlushRecording) is sent or the
+/// app exits.
+///
+/// Each flush writes the frames kept since the last one, in the [format](ScreenDiagsRecordFormat)
+/// of the recorder. So that a long session does not fill the memory, at most
+/// [max_frames](ScreenDiagsRecorder::max_frames) are kept between flushes.
+#[derive(Resource, Clone, Debug)]
+pub struct ScreenDiagsRecorder {
+    /// The file to write to.
+    pub path: PathBuf,
+    /// How the frames are written.
+    pub format: ScreenDiagsRecordFormat,
+    /// Whether to flush when the app exits.
+    pub flush_on_exit: bool,
+    /// The most frames kept at once. Once this many are kept, the formats that append to the
+    /// file flush them, and the [summary](ScreenDiagsRecordFormat::JsonSummary) forgets the
+    /// oldest, to summarise the latest this many. By default, ten minutes at 60 FPS.
+    pub max_frames: usize,
+    frames: VecDeque<RecordedFrame>,
+    frame: u64,
+}
+
+/// How the [recorder](ScreenDiagsRecorder) writes the frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScreenDiagsRecordFormat {
+    /// One CSV line per frame, appended to the file, with a header if the file is new.
+    #[default]
+    Csv,
+    /// One JSON object per frame and line, appended to the file.
+    JsonLines,
+    /// One JSON document summarising the frames, with the lowest, average and highest FPS and the
+    /// percentiles of the frame time. Each flush replaces the file.
+    JsonSummary,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct RecordedFrame {
+    frame: u64,
+    // When the frame ended, in seconds since startup
+    time: f64,
+    // How long the frame took, in seconds
+    frame_time: f64,
+}
+
+impl ScreenDiagsRecorder {
+    /// A recorder that writes CSV to this file, and flushes when the app exits.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            format: ScreenDiagsRecordFormat::default(),
+            flush_on_exit: true,
+            max_frames: 36_000,
+            frames: VecDeque::new(),
+            frame: 0,
+        }
+    }
+
+    /// The number of frames kept since the last flush.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Whether no frames have been kept since the last flush.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Uses this format.
+    pub fn with_format(mut self, format: ScreenDiagsRecordFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Keeps at most this many frames at once.
+    pub fn with_max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = max_frames;
+        self
+    }
+
+    /// Writes the frames kept so far to the file, and forgets them.
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self.format {
+            ScreenDiagsRecordFormat::Csv => self.write_csv()?,
+            ScreenDiagsRecordFormat::JsonLines => self.write_json_lines()?,
+            ScreenDiagsRecordFormat::JsonSummary => self.write_json_summary()?,
+        }
+        self.frames.clear();
+        Ok(())
+    }
+
+    // Keeps a frame, making room for it first if there are as many as there can be
+    fn keep(&mut self, frame: RecordedFrame) {
+        if self.frames.len() >= self.max_frames.max(1) {
+            if self.format == ScreenDiagsRecordFormat::JsonSummary {
+                self.frames.pop_front();
+            } else {
+                self.flush_or_log();
+            }
+        }
+        self.frames.push_back(frame);
+    }
+
+    // Flushes, logging any error. The frames are forgotten even then, so that they don't pile up
+    // while the file can't be written
+    fn flush_or_log(&mut self) {
+        if let Err(error) = self.flush() {
+            self.frames.clear();
+            error!(
+                "Failed to write the frame recording to {}: {}",
+                self.path.display(),
+                error
+            );
+        }
+    }
+
+    fn append(&self) -> io::Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+    }
+
+    fn write_csv(&self) -> io::Result<()> {
+        let file = self.append()?;
+        let new_file = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+        if new_file {
+            writeln!(writer, "frame,time_s,frame_time_ms,fps")?;
+        }
+        for frame in &self.frames {
+            writeln!(
+                writer,
+                "{},{:.6},{:.3},{:.1}",
+                frame.frame,
+                frame.time,
+                frame.frame_time * 1000.0,
+                1.0 / frame.frame_time
+            )?;
+        }
+        writer.flush()
+    }
+
+    fn write_json_lines(&self) -> io::Result<()> {
+        let mut writer = BufWriter::new(self.append()?);
+        for frame in &self.frames {
+            writeln!(
+                writer,
+                r#"{{"frame":{},"time_s":{:.6},"frame_time_ms":{:.3},"fps":{:.1}}}"#,
+                frame.frame,
+                frame.time,
+                frame.frame_time * 1000.0,
+                1.0 / frame.frame_time
+            )?;
+        }
+        writer.flush()
+    }
+
+    fn write_json_summary(&self) -> io::Result<()> {
+        let mut sorted: Vec<f64> = self.frames.iter().map(|frame| frame.frame_time).collect();
+        sorted.sort_unstable_by(f64::total_cmp);
+        let (Some(&shortest), Some(&longest)) = (sorted.first(), sorted.last()) else {
+            return Ok(());
+        };
+        let total: f64 = sorted.iter().sum();
+        let ms = |fraction| percentile(&sorted, fraction) * 1000.0;
+
+        let mut writer = BufWriter::new(File::create(&self.path)?);
+        writeln!(writer, "{{")?;
+        writeln!(writer, r#"  "frames": {},"#, sorted.len())?;
+        writeln!(writer, r#"  "duration_s": {:.6},"#, total)?;
+        writeln!(writer, r#"  "fps_min": {:.1},"#, 1.0 / longest)?;
+        writeln!(
+            writer,
+            r#"  "fps_avg": {:.1},"#,
+            sorted.len() as f64 / total
+        )?;
+        writeln!(writer, r#"  "fps_max": {:.1},"#, 1.0 / shortest)?;
+        writeln!(
+            writer,
+            r#"  "fps_1_percent_low": {:.1},"#,
+            1.0 / percentile(&sorted, 0.99)
+        )?;
+        writeln!(
+            writer,
+            r#"  "fps_0_1_percent_low": {:.1},"#,
+            1.0 / percentile(&sorted, 0.999)
+        )?;
+        writeln!(writer, r#"  "frame_time_ms_p50": {:.3},"#, ms(0.5))?;
+        writeln!(writer, r#"  "frame_time_ms_p90": {:.3},"#, ms(0.9))?;
+        writeln!(writer, r#"  "frame_time_ms_p99": {:.3}"#, ms(0.99))?;
+        writeln!(writer, "}}")?;
+        writer.flush()
+    }
+}
+
+/// An event that writes the frames kept by the [recorder](ScreenDiagsRecorder) to its file.
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct ScreenDiagsFlushRecording;
+
+pub(crate) fn record_frames(time: Res<Time>, mut recorder: ResMut<ScreenDiagsRecorder>) {
+    let frame_time = time.raw_delta_seconds_f64();
+    recorder.frame += 1;
+    if frame_time <= 0.0 {
+        return;
+    }
+
+    let frame = recorder.frame;
+    recorder.keep(RecordedFrame {
+        frame,
+        time: time.raw_elapsed_seconds_f64(),
+        frame_time,
+    });
+}
+
+// Flushes on request, and when the app is about to exit
+pub(crate) fn flush_recording(
+    mut flush_events: EventReader<ScreenDiagsFlushRecording>,
+    mut exit_events: EventReader<AppExit>,
+    mut recorder: ResMut<ScreenDiagsRecorder>,
+) {
+    let flush = flush_events.iter().count() > 0;
+    let exit = exit_events.iter().count() > 0 && recorder.flush_on_exit;
+    if !(flush || exit) || recorder.is_empty() {
+        return;
+    }
+    recorder.flush_or_log();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    fn frame(frame: u64) -> RecordedFrame {
+        RecordedFrame {
+            frame,
+            time: frame as f64 * 0.016,
+            frame_time: 0.016,
+        }
+    }
+
+    #[test]
+    fn flushes_to_the_file_once_full() {
+        let path = env::temp_dir().join(format!("screen_diags_{}_cap.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut recorder = ScreenDiagsRecorder::new(&path).with_max_frames(3);
+        for number in 1..=7 {
+            recorder.keep(frame(number));
+            assert!(recorder.len() <= 3);
+        }
+        let written = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // The header and the first six frames, with the seventh still kept
+        let frames: Vec<_> = written.lines().skip(1).map(|line| &line[..2]).collect();
+        assert_eq!(frames, ["1,", "2,", "3,", "4,", "5,", "6,"]);
+        assert_eq!(recorder.len(), 1);
+    }
+
+    #[test]
+    fn summarises_the_latest_frames_once_full() {
+        let path = env::temp_dir().join(format!("screen_diags_{}_cap.json", std::process::id()));
+        let mut recorder = ScreenDiagsRecorder::new(&path)
+            .with_format(ScreenDiagsRecordFormat::JsonSummary)
+            .with_max_frames(3);
+        for number in 1..=7 {
+            recorder.keep(frame(number));
+        }
+        assert!(!path.exists());
+        let kept: Vec<_> = recorder.frames.iter().map(|frame| frame.frame).collect();
+        assert_eq!(kept, [5, 6, 7]);
+    }
+}
@@ -0,0 +1,81 @@
+//! Taking screenshots with a key, with or without the overlays.
+
+use std::path::PathBuf;
+
+use bevy::{prelude::*, render::view::screenshot::ScreenshotManager, window::PrimaryWindow};
+
+use crate::{ScreenDiagsFrames, ScreenDiagsOverlay};
+
+/// Insert this resource to save a screenshot of the primary window when a key chord is pressed.
+///
+/// The overlays can be hidden for the frame that is captured, for clean screenshots, or left in
+/// to show the diagnostics alongside the frame.
+#[derive(Resource, Clone, Debug)]
+pub struct ScreenDiagsScreenshot {
+    /// The keys in the chord.
+    pub keys: Vec<KeyCode>,
+    /// Whether the overlays are in the screenshots.
+    pub overlay: ScreenDiagsScreenshotOverlay,
+    /// The directory the screenshots are saved in, as `screenshot-<frame>.png`.
+    pub directory: PathBuf,
+}
+
+/// Whether the overlays are in the [screenshots](ScreenDiagsScreenshot).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScreenDiagsScreenshotOverlay {
+    /// The overlays are hidden for the frame that is captured.
+    #[default]
+    Exclude,
+    /// The overlays are burned into the screenshot.
+    Include,
+}
+
+impl Default for ScreenDiagsScreenshot {
+    fn default() -> Self {
+        Self {
+            keys: vec![KeyCode::F2],
+            overlay: ScreenDiagsScreenshotOverlay::default(),
+            directory: PathBuf::from("."),
+        }
+    }
+}
+
+// Asks for a screenshot of this frame when the chord is pressed, hiding the overlays for it.
+// They are shown again next frame, when their visibility is updated.
+pub(crate) fn take_screenshot(
+    screenshot: Res<ScreenDiagsScreenshot>,
+    keyboard: Res<Input<KeyCode>>,
+    frames: Res<ScreenDiagsFrames>,
+    window_query: Query<Entity, With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+    mut overlay_query: Query<&mut Visibility, With<ScreenDiagsOverlay>>,
+) {
+    let keys = screenshot.keys.iter().copied();
+    if screenshot.keys.is_empty()
+        || !keys.clone().all(|key| keyboard.pressed(key))
+        || !keyboard.any_just_pressed(keys)
+    {
+        return;
+    }
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    let path = screenshot
+        .directory
+        .join(format!("screenshot-{}.png", frames.total()));
+    if screenshot_manager
+        .save_screenshot_to_disk(window, &path)
+        .is_err()
+    {
+        warn!("A screenshot was already asked for this frame");
+        return;
+    }
+    info!("Saving a screenshot to {}", path.display());
+
+    if screenshot.overlay == ScreenDiagsScreenshotOverlay::Exclude {
+        for mut visibility in overlay_query.iter_mut() {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Spotting frames that take much longer than those around them.
+
+use bevy::{prelude::*, utils::Duration, window::PrimaryWindow};
+
+use crate::{
+    history::{self, ScreenDiagsHistory},
+    ScreenDiagsConfig, ScreenDiagsFrames,
+};
+
+// Too few frames make for a poor average, as when the app starts
+const MIN_FRAMES: usize = 10;
+
+/// The spike detector resource, which counts the frames that take longer than the
+/// [threshold](ScreenDiagsSpikes::threshold) times the average over the
+/// [window](crate::ScreenDiagsConfig::window), and sends a [FrameSpikeEvent] for each.
+///
+/// The frames left out of the [history](ScreenDiagsHistory) are not counted, nor is the first
+/// frame after them, which the OS throttled while the window was
+/// [unfocused](crate::ScreenDiagsConfig::pause_when_unfocused).
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct ScreenDiagsSpikes {
+    /// How many times longer than the average a frame must take to be a spike.
+    pub threshold: f64,
+    count: u64,
+}
+
+impl Default for ScreenDiagsSpikes {
+    fn default() -> Self {
+        Self {
+            threshold: 2.0,
+            count: 0,
+        }
+    }
+}
+
+impl ScreenDiagsSpikes {
+    /// The number of spikes since the app started, or since the count was reset.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Starts counting the spikes again from zero.
+    pub fn reset(&mut self) {
+        self.count = 0;
+    }
+}
+
+/// An event sent when a frame takes much longer than the average.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct FrameSpikeEvent {
+    /// How long the frame took.
+    pub duration: Duration,
+    /// The number of the frame, counting from zero when the app started.
+    pub frame: u64,
+}
+
+// Compares the last frame with the average, before it joins the history
+pub(crate) fn detect_spikes(
+    time: Res<Time>,
+    config: Res<ScreenDiagsConfig>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    history: Res<ScreenDiagsHistory>,
+    frames: Res<ScreenDiagsFrames>,
+    mut spikes: ResMut<ScreenDiagsSpikes>,
+    mut spike_events: EventWriter<FrameSpikeEvent>,
+) {
+    let frame_time = time.raw_delta_seconds_f64();
+    let unfocused = config.pause_when_unfocused && history::is_unfocused(&window_query);
+    if frame_time <= 0.0 || unfocused || history.is_paused() {
+        return;
+    }
+    let Some(average) = history.average_frame_time(MIN_FRAMES) else {
+        return;
+    };
+    if frame_time > average * spikes.threshold {
+        spikes.count += 1;
+        spike_events.send(FrameSpikeEvent {
+            duration: time.raw_delta(),
+            frame: frames.total().saturating_sub(1),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::time::{TimePlugin, TimeUpdateStrategy};
+
+    use super::*;
+    use crate::history::record_frame_time;
+
+    // An app of a focused window that pauses while unfocused, which has taken enough frames of
+    // 10 ms for an average
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(TimePlugin)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                10,
+            )))
+            .insert_resource(ScreenDiagsConfig {
+                pause_when_unfocused: true,
+                ..Default::default()
+            })
+            .init_resource::<ScreenDiagsHistory>()
+            .init_resource::<ScreenDiagsFrames>()
+            .init_resource::<ScreenDiagsSpikes>()
+            .add_event::<FrameSpikeEvent>()
+            .add_systems(Update, (detect_spikes, record_frame_time).chain());
+        app.world.spawn((Window::default(), PrimaryWindow));
+        for _ in 0..20 {
+            app.update();
+        }
+        app
+    }
+
+    // Runs a frame of this many milliseconds, returning the spikes counted so far
+    fn frame(app: &mut App, millis: u64) -> u64 {
+        let duration = Duration::from_millis(millis);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(duration));
+        app.update();
+        app.world.resource::<ScreenDiagsSpikes>().count()
+    }
+
+    fn set_focused(app: &mut App, focused: bool) {
+        let mut window_query = app.world.query::<&mut Window>();
+        window_query.single_mut(&mut app.world).focused = focused;
+    }
+
+    #[test]
+    fn counts_frames_past_the_threshold_times_the_average() {
+        let mut app = app();
+        assert_eq!(frame(&mut app, 10), 0);
+        // Just under twice the average is not a spike
+        assert_eq!(frame(&mut app, 19), 0);
+        assert_eq!(frame(&mut app, 30), 1);
+        assert_eq!(frame(&mut app, 10), 1);
+
+        app.world.resource_mut::<ScreenDiagsSpikes>().threshold = 4.0;
+        assert_eq!(frame(&mut app, 30), 1);
+        assert_eq!(frame(&mut app, 50), 2);
+    }
+
+    #[test]
+    fn leaves_out_the_frames_while_unfocused_and_the_first_after() {
+        let mut app = app();
+        set_focused(&mut app, false);
+        assert_eq!(frame(&mut app, 100), 0);
+        assert_eq!(frame(&mut app, 100), 0);
+        set_focused(&mut app, true);
+        assert_eq!(frame(&mut app, 100), 0);
+        assert_eq!(frame(&mut app, 10), 0);
+        assert_eq!(frame(&mut app, 30), 1);
+    }
+}
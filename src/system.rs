@@ -0,0 +1,104 @@
+//! Readings of the process from the operating system, taken in a background task.
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use futures_lite::future;
+use sysinfo::{CpuExt, Pid, ProcessExt, System, SystemExt};
+
+use crate::{ScreenDiagsConfig, ScreenDiagsMetric, ScreenDiagsOverlay, ScreenDiagsState};
+
+/// The resident memory of the process, in MiB.
+pub(crate) const MEMORY: DiagnosticId =
+    DiagnosticId::from_u128(0x6f2d_1c4a_83b5_4e07_9a61_d2f8_0b3e_57c9);
+
+/// The CPU used by the process, as a percentage of all the cores.
+pub(crate) const CPU_PROCESS: DiagnosticId =
+    DiagnosticId::from_u128(0x2b87_e4d0_5f1c_4a93_b06e_7c25_d9a1_38f4);
+
+/// The CPU used by the whole system, as a percentage of all the cores.
+pub(crate) const CPU_TOTAL: DiagnosticId =
+    DiagnosticId::from_u128(0x94c3_0a7e_e2d6_41b8_8f5a_1b60_c7e3_2d95);
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// The system handle, which is moved into the task while it is refreshed.
+#[derive(Resource)]
+pub(crate) struct SystemInfo {
+    pid: Option<Pid>,
+    system: Option<System>,
+    task: Option<Task<(System, SystemSample)>>,
+}
+
+// What one refresh of the system found
+struct SystemSample {
+    memory: Option<f64>,
+    cpu_process: Option<f64>,
+    cpu_total: f64,
+}
+
+pub(crate) fn register(app: &mut App) {
+    app.register_diagnostic(Diagnostic::new(MEMORY, "Memory", 1).with_suffix("MiB"))
+        .register_diagnostic(Diagnostic::new(CPU_PROCESS, "CPU (process)", 1).with_suffix("%"))
+        .register_diagnostic(Diagnostic::new(CPU_TOTAL, "CPU (total)", 1).with_suffix("%"))
+        .insert_resource(SystemInfo {
+            pid: sysinfo::get_current_pid().ok(),
+            system: Some(System::new()),
+            task: None,
+        });
+}
+
+/// Collects the last refresh if it has finished, and starts another when the display is due and
+/// any row shows the readings.
+pub(crate) fn sample_system_info(
+    state: Res<ScreenDiagsState>,
+    config: Res<ScreenDiagsConfig>,
+    overlay_query: Query<&ScreenDiagsOverlay>,
+    mut info: ResMut<SystemInfo>,
+    mut diagnostics: Diagnostics,
+) {
+    let info = &mut *info;
+    if let Some(task) = &mut info.task {
+        let Some((system, sample)) = future::block_on(future::poll_once(task)) else {
+            return;
+        };
+        info.task = None;
+        info.system = Some(system);
+        if let Some(memory) = sample.memory {
+            diagnostics.add_measurement(MEMORY, || memory);
+        }
+        if let Some(cpu) = sample.cpu_process {
+            diagnostics.add_measurement(CPU_PROCESS, || cpu);
+        }
+        diagnostics.add_measurement(CPU_TOTAL, || sample.cpu_total);
+    }
+
+    if !state.due || !state.enabled() {
+        return;
+    }
+    // A refresh takes a task and allocates, so it is left out while nothing shows it
+    let shows = |config: &ScreenDiagsConfig| {
+        config.shows(ScreenDiagsMetric::Memory) || config.shows(ScreenDiagsMetric::Cpu)
+    };
+    if !shows(&config) && !overlay_query.iter().any(|overlay| shows(&overlay.config)) {
+        return;
+    }
+    let (Some(pid), Some(mut system)) = (info.pid, info.system.take()) else {
+        return;
+    };
+    info.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+        // CPU usage is measured since the last refresh, so the first sample reads zero
+        system.refresh_cpu();
+        system.refresh_process(pid);
+        let cores = system.cpus().len().max(1) as f64;
+        let process = system.process(pid);
+        let sample = SystemSample {
+            memory: process.map(|process| process.memory() as f64 / MIB),
+            cpu_process: process.map(|process| process.cpu_usage() as f64 / cores),
+            cpu_total: system.global_cpu_info().cpu_usage() as f64,
+        };
+        (system, sample)
+    }));
+}
@@ -0,0 +1,91 @@
+//! Printing the diagnostics to the terminal, for dedicated servers and CI runs with no window.
+
+use std::io::Write as _;
+
+use bevy::prelude::*;
+
+use crate::{
+    overlay::write_row, MetricSources, ScreenDiagsConfig, ScreenDiagsReadings, ScreenDiagsState,
+};
+
+/// How the diagnostics are printed to the terminal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScreenDiagsTerminalStyle {
+    /// A block with one row per line, as on the screen, printed after the last.
+    #[default]
+    Block,
+    /// All the rows on one line, which is rewritten in place each time.
+    Line,
+}
+
+/// The terminal output resource. While it exists, the rows of the [config](ScreenDiagsConfig) are
+/// printed to stdout at its interval, formatted as on the screen, while the display is
+/// [enabled](ScreenDiagsState::enabled).
+///
+/// Like the [log](crate::ScreenDiagsLog), the output does not need a window or UI, so a server can
+/// share the config of the clients.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct ScreenDiagsTerminal {
+    /// How the rows are printed.
+    pub style: ScreenDiagsTerminalStyle,
+    readings: ScreenDiagsReadings,
+    text: String,
+}
+
+impl ScreenDiagsTerminal {
+    /// Prints a block with one row per line.
+    pub fn block() -> Self {
+        Self::default()
+    }
+
+    /// Rewrites a single line with all the rows.
+    pub fn line() -> Self {
+        Self {
+            style: ScreenDiagsTerminalStyle::Line,
+            ..Default::default()
+        }
+    }
+}
+
+// Prints the rows when the display is due, unless it is disabled
+pub(crate) fn print_to_terminal(
+    mut sources: MetricSources,
+    config: Res<ScreenDiagsConfig>,
+    state: Res<ScreenDiagsState>,
+    mut terminal: ResMut<ScreenDiagsTerminal>,
+) {
+    if !state.due || !state.enabled() || config.rows.is_empty() {
+        return;
+    }
+
+    let ScreenDiagsTerminal {
+        style,
+        readings,
+        text,
+    } = terminal.bypass_change_detection();
+    text.clear();
+    if *style == ScreenDiagsTerminalStyle::Line {
+        // Back to the start of the line, and clear it
+        text.push_str("\r\x1b[2K");
+    }
+    for (index, row) in config.rows.iter().enumerate() {
+        readings.sample(row.metric, &mut sources);
+        match style {
+            ScreenDiagsTerminalStyle::Block => write_row(text, index, row, readings),
+            ScreenDiagsTerminalStyle::Line => {
+                if index > 0 {
+                    text.push_str(", ");
+                }
+                write_row(text, 0, row, readings);
+            }
+        }
+    }
+    if *style == ScreenDiagsTerminalStyle::Block {
+        text.push('\n');
+    }
+
+    // Nothing can be done if stdout is closed
+    let mut stdout = std::io::stdout().lock();
+    let _ = stdout.write_all(text.as_bytes());
+    let _ = stdout.flush();
+}
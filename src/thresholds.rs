@@ -0,0 +1,213 @@
+//! Telling the game when the FPS drops below a threshold, and when it recovers.
+
+use bevy::{diagnostic::DiagnosticsStore, prelude::*, utils::Duration};
+
+use crate::{extract_fps, ScreenDiagsConfig};
+
+/// Insert this resource to be sent an [FpsBelowThreshold] event when the FPS drops below a
+/// threshold, and an [FpsRecovered] event when it comes back, for example to lower the graphics
+/// settings.
+///
+/// The FPS must rise to [recover_above](ScreenDiagsFpsThreshold::recover_above) to recover, which
+/// should be higher than the threshold, and must stay past either for
+/// [hold](ScreenDiagsFpsThreshold::hold), so that an FPS near the threshold does not send a
+/// stream of events. The FPS is [smoothed](ScreenDiagsConfig::smoothing) as the FPS row is.
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct ScreenDiagsFpsThreshold {
+    /// The FPS below which an [FpsBelowThreshold] event is sent.
+    pub below: f64,
+    /// The FPS above which an [FpsRecovered] event is sent, once the FPS has dropped.
+    pub recover_above: f64,
+    /// How long the FPS must stay below the threshold, or above the recovery, before the event.
+    pub hold: Duration,
+    breached: bool,
+    // How long the FPS has been past the threshold that would change the state
+    held: Duration,
+}
+
+impl ScreenDiagsFpsThreshold {
+    /// A threshold at this FPS, which recovers 10% above it, after one second either way.
+    pub fn new(below: f64) -> Self {
+        Self {
+            below,
+            recover_above: below * 1.1,
+            hold: Duration::from_secs(1),
+            breached: false,
+            held: Duration::ZERO,
+        }
+    }
+
+    /// Whether the FPS is below the threshold, and has not yet recovered.
+    pub fn is_breached(&self) -> bool {
+        self.breached
+    }
+
+    // Counts how long the FPS has been past the threshold that would change the state, and
+    // changes it once that has lasted the hold. Returns whether the state changed.
+    fn update(&mut self, fps: f64, delta: Duration) -> bool {
+        let crossing = if self.breached {
+            fps > self.recover_above
+        } else {
+            fps < self.below
+        };
+        if !crossing {
+            self.held = Duration::ZERO;
+            return false;
+        }
+        self.held += delta;
+        if self.held < self.hold {
+            return false;
+        }
+        self.held = Duration::ZERO;
+        self.breached = !self.breached;
+        true
+    }
+}
+
+impl Default for ScreenDiagsFpsThreshold {
+    fn default() -> Self {
+        Self::new(30.0)
+    }
+}
+
+/// An event sent when the FPS drops below the [threshold](ScreenDiagsFpsThreshold).
+#[derive(Event, Clone, Copy, Debug)]
+pub struct FpsBelowThreshold {
+    /// The FPS when the event was sent.
+    pub fps: f64,
+    /// The threshold it dropped below.
+    pub threshold: f64,
+}
+
+/// An event sent when the FPS recovers after dropping below the
+/// [threshold](ScreenDiagsFpsThreshold).
+#[derive(Event, Clone, Copy, Debug)]
+pub struct FpsRecovered {
+    /// The FPS when the event was sent.
+    pub fps: f64,
+    /// How long the FPS stayed below the threshold, counting from the drop event.
+    pub below_for: Duration,
+}
+
+// Sends the events when the FPS has stayed past the threshold long enough
+pub(crate) fn check_fps_threshold(
+    time: Res<Time>,
+    diagnostics: Res<DiagnosticsStore>,
+    config: Res<ScreenDiagsConfig>,
+    mut threshold: ResMut<ScreenDiagsFpsThreshold>,
+    mut below_events: EventWriter<FpsBelowThreshold>,
+    mut recovered_events: EventWriter<FpsRecovered>,
+    mut breached_at: Local<Duration>,
+) {
+    let Some(fps) = extract_fps(&diagnostics, config.smoothing) else {
+        return;
+    };
+    if !threshold.update(fps, time.raw_delta()) {
+        return;
+    }
+    if threshold.breached {
+        *breached_at = time.raw_elapsed();
+        below_events.send(FpsBelowThreshold {
+            fps,
+            threshold: threshold.below,
+        });
+    } else {
+        recovered_events.send(FpsRecovered {
+            fps,
+            below_for: time.raw_elapsed().saturating_sub(*breached_at),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        diagnostic::{Diagnostic, DiagnosticMeasurement, FrameTimeDiagnosticsPlugin},
+        utils::Instant,
+    };
+
+    use super::*;
+    use crate::ScreenDiagsSmoothing;
+
+    const FRAME: Duration = Duration::from_millis(100);
+
+    // Feeds the same FPS for this many frames, returning the frames on which the state changed
+    fn feed(threshold: &mut ScreenDiagsFpsThreshold, fps: f64, frames: usize) -> Vec<usize> {
+        (0..frames)
+            .filter(|_| threshold.update(fps, FRAME))
+            .collect()
+    }
+
+    #[test]
+    fn breaches_once_held_below() {
+        let mut threshold = ScreenDiagsFpsThreshold::new(30.0);
+        assert_eq!(feed(&mut threshold, 25.0, 20), [9]);
+        assert!(threshold.is_breached());
+    }
+
+    #[test]
+    fn a_short_drop_does_not_breach() {
+        let mut threshold = ScreenDiagsFpsThreshold::new(30.0);
+        assert!(feed(&mut threshold, 25.0, 9).is_empty());
+        assert!(feed(&mut threshold, 40.0, 1).is_empty());
+        // The time held starts again after the FPS comes back
+        assert!(feed(&mut threshold, 25.0, 9).is_empty());
+        assert!(!threshold.is_breached());
+    }
+
+    #[test]
+    fn recovers_only_above_the_recovery() {
+        let mut threshold = ScreenDiagsFpsThreshold::new(30.0);
+        feed(&mut threshold, 25.0, 10);
+        assert!(threshold.is_breached());
+        // Between the threshold and the recovery, nothing changes
+        assert!(feed(&mut threshold, 32.0, 20).is_empty());
+        assert!(threshold.is_breached());
+        assert_eq!(feed(&mut threshold, 40.0, 10), [9]);
+        assert!(!threshold.is_breached());
+    }
+
+    // The FPS of the drop events sent for these FPS measurements, with no hold
+    fn drops(fps: &[f64], smoothing: ScreenDiagsSmoothing) -> Vec<f64> {
+        let mut diagnostic = Diagnostic::new(FrameTimeDiagnosticsPlugin::FPS, "fps", 20);
+        let start = Instant::now();
+        for (frame, value) in fps.iter().enumerate() {
+            diagnostic.add_measurement(DiagnosticMeasurement {
+                time: start + Duration::from_millis(frame as u64),
+                value: *value,
+            });
+        }
+        let mut diagnostics = DiagnosticsStore::default();
+        diagnostics.add(diagnostic);
+
+        let mut app = App::new();
+        app.insert_resource(diagnostics)
+            .insert_resource(ScreenDiagsConfig {
+                smoothing,
+                ..Default::default()
+            })
+            .insert_resource(ScreenDiagsFpsThreshold {
+                hold: Duration::ZERO,
+                ..ScreenDiagsFpsThreshold::new(30.0)
+            })
+            .init_resource::<Time>()
+            .add_event::<FpsBelowThreshold>()
+            .add_event::<FpsRecovered>()
+            .add_systems(Update, check_fps_threshold);
+        app.update();
+        let events = app.world.resource::<Events<FpsBelowThreshold>>();
+        events
+            .iter_current_update_events()
+            .map(|event| event.fps)
+            .collect()
+    }
+
+    #[test]
+    fn judges_the_fps_as_smoothed() {
+        let fps = [60.0, 60.0, 60.0, 20.0];
+        // The average of 50 is fine, but the last frame alone is not
+        assert!(drops(&fps, ScreenDiagsSmoothing::Average).is_empty());
+        assert_eq!(drops(&fps, ScreenDiagsSmoothing::Window(1)), [20.0]);
+    }
+}
@@ -0,0 +1,173 @@
+//! The time taken by each system, measured from the tracing spans that Bevy puts around them.
+
+use std::{
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use bevy::{
+    prelude::*,
+    utils::{
+        tracing::{
+            field::{Field, Visit},
+            span::{Attributes, Id},
+            Subscriber,
+        },
+        Duration, HashMap,
+    },
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::{ScreenDiagsFrames, ScreenDiagsState};
+
+/// The system timings resource, which keeps the slowest systems of the last interval for a
+/// [row](crate::ScreenDiagsRow::slowest_systems).
+///
+/// Bevy only makes a tracing span for each system with its `trace` feature, which this feature
+/// turns on. The spans are timed by the [layer](ScreenDiagsSystemTimings::layer), which must be
+/// added to the tracing subscriber. As the `LogPlugin` sets up its own subscriber, disable it and
+/// set up the subscriber yourself, with
+/// `tracing_subscriber::registry().with(timings.layer()).with(tracing_subscriber::fmt::layer()).init()`.
+#[derive(Resource, Clone)]
+pub struct ScreenDiagsSystemTimings {
+    /// How many systems to show.
+    pub count: usize,
+    times: Arc<Mutex<HashMap<String, Duration>>>,
+    // The slowest systems, with their average time per frame over the last interval
+    slowest: Vec<(String, Duration)>,
+    last_frame: u64,
+}
+
+impl ScreenDiagsSystemTimings {
+    /// Keeps this many of the slowest systems.
+    pub fn new(count: usize) -> Self {
+        Self {
+            count,
+            times: Arc::default(),
+            slowest: Vec::new(),
+            last_frame: 0,
+        }
+    }
+
+    /// The tracing layer that times the systems for this resource.
+    pub fn layer(&self) -> ScreenDiagsTimingLayer {
+        ScreenDiagsTimingLayer {
+            times: self.times.clone(),
+        }
+    }
+
+    // Writes a line for each of the slowest systems
+    pub(crate) fn write(&self, text: &mut String) {
+        for (name, time) in &self.slowest {
+            write!(text, "\n  {:.2} ms {}", time.as_secs_f64() * 1000.0, name).unwrap();
+        }
+    }
+}
+
+impl Default for ScreenDiagsSystemTimings {
+    fn default() -> Self {
+        Self::new(5)
+    }
+}
+
+/// The tracing layer that times the systems, made by [ScreenDiagsSystemTimings::layer].
+pub struct ScreenDiagsTimingLayer {
+    times: Arc<Mutex<HashMap<String, Duration>>>,
+}
+
+// Kept with each system span
+struct SystemSpan {
+    name: String,
+    entered: Option<Instant>,
+}
+
+struct NameVisitor<'a>(&'a mut String);
+
+impl Visit for NameVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "name" {
+            self.0.push_str(value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "name" {
+            // Strings that come through here are quoted
+            let name = format!("{:?}", value);
+            self.0.push_str(name.trim_matches('"'));
+        }
+    }
+}
+
+impl<S> Layer<S> for ScreenDiagsTimingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != "system" {
+            return;
+        }
+        let mut name = String::new();
+        attrs.record(&mut NameVisitor(&mut name));
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SystemSpan {
+                name,
+                entered: None,
+            });
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(system) = extensions.get_mut::<SystemSpan>() {
+            system.entered = Some(Instant::now());
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(system) = extensions.get_mut::<SystemSpan>() else {
+            return;
+        };
+        let Some(entered) = system.entered.take() else {
+            return;
+        };
+        let mut times = self.times.lock().unwrap();
+        match times.get_mut(&system.name) {
+            Some(time) => *time += entered.elapsed(),
+            None => {
+                times.insert(system.name.clone(), entered.elapsed());
+            }
+        }
+    }
+}
+
+// Picks out the slowest systems when the readings are due, and starts timing afresh
+pub(crate) fn collect_system_timings(
+    state: Res<ScreenDiagsState>,
+    frames: Res<ScreenDiagsFrames>,
+    mut timings: ResMut<ScreenDiagsSystemTimings>,
+) {
+    if !state.due {
+        return;
+    }
+
+    let timings = &mut *timings;
+    let frame_count = frames.total().saturating_sub(timings.last_frame).max(1) as u32;
+    timings.last_frame = frames.total();
+
+    let mut times = timings.times.lock().unwrap();
+    timings.slowest.clear();
+    timings
+        .slowest
+        .extend(times.drain().map(|(name, time)| (name, time / frame_count)));
+    timings.slowest.sort_unstable_by(|(_, a), (_, b)| b.cmp(a));
+    timings.slowest.truncate(timings.count);
+}
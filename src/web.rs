@@ -0,0 +1,52 @@
+//! Readings of the browser, for web builds.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+
+// Whether the tab is hidden, when the browser throttles or stops its frames
+static HIDDEN: AtomicBool = AtomicBool::new(false);
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+// The size of a page of WebAssembly memory
+const WASM_PAGE: f64 = 64.0 * 1024.0;
+
+/// The size of the WebAssembly memory, in MiB. It only ever grows.
+pub(crate) fn wasm_memory() -> f64 {
+    core::arch::wasm32::memory_size(0) as f64 * WASM_PAGE / MIB
+}
+
+/// The JavaScript heap in use, in MiB, from the non-standard `performance.memory`. Only
+/// Chromium-based browsers have it.
+pub(crate) fn js_heap_used() -> Option<f64> {
+    let performance = web_sys::window()?.performance()?;
+    let memory = js_sys::Reflect::get(&performance, &JsValue::from_str("memory")).ok()?;
+    let used = js_sys::Reflect::get(&memory, &JsValue::from_str("usedJSHeapSize")).ok()?;
+    Some(used.as_f64()? / MIB)
+}
+
+/// Listens for the tab being hidden and shown again.
+pub(crate) fn listen_for_visibility() {
+    let Some(document) = web_sys::window().and_then(|window| window.document()) else {
+        return;
+    };
+    HIDDEN.store(document.hidden(), Ordering::Relaxed);
+    let listener = Closure::<dyn Fn()>::new(|| {
+        if let Some(document) = web_sys::window().and_then(|window| window.document()) {
+            HIDDEN.store(document.hidden(), Ordering::Relaxed);
+        }
+    });
+    // The listener lasts as long as the page
+    if document
+        .add_event_listener_with_callback("visibilitychange", listener.as_ref().unchecked_ref())
+        .is_ok()
+    {
+        listener.forget();
+    }
+}
+
+/// Whether the browser is throttling the frames, because the tab is hidden.
+pub(crate) fn is_throttled() -> bool {
+    HIDDEN.load(Ordering::Relaxed)
+}
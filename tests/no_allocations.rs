@@ -0,0 +1,142 @@
+//! Checks that taking and formatting the readings does not allocate once the app has warmed up.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    fmt::Write,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use bevy::{
+    diagnostic::DiagnosticsPlugin,
+    ecs::schedule::ExecutorKind,
+    prelude::*,
+    time::TimeUpdateStrategy,
+    utils::Duration,
+    window::{WindowResized, WindowScaleFactorChanged},
+};
+
+use bevy_screen_diags::{
+    ScreenDiagsConfig, ScreenDiagsFont, ScreenDiagsPlugin, ScreenDiagsRow, ScreenDiagsRows,
+    ScreenDiagsSet, ScreenDiagsText, ScreenDiagsUiPlugin,
+};
+
+// Counts the allocations made on a thread while it is counting
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+}
+
+fn count_allocation() {
+    if COUNTING.with(Cell::get) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const WARM_UP_FRAMES: usize = 200;
+const COUNTED_FRAMES: usize = 200;
+
+fn start_counting() {
+    COUNTING.with(|counting| counting.set(true));
+}
+
+fn stop_counting() {
+    COUNTING.with(|counting| counting.set(false));
+}
+
+#[test]
+fn updates_do_not_allocate() {
+    let config = ScreenDiagsConfig {
+        rows: vec![
+            ScreenDiagsRow::fps(),
+            ScreenDiagsRow::frame_time(),
+            ScreenDiagsRow::entity_count(),
+            ScreenDiagsRow::fps_min_avg_max(),
+            ScreenDiagsRow::fps_lows(),
+            ScreenDiagsRow::frame_count(),
+            ScreenDiagsRow::uptime(),
+            ScreenDiagsRow::fixed_updates(),
+            ScreenDiagsRow::ecs_counts(),
+            ScreenDiagsRow::spikes(),
+            ScreenDiagsRow::custom("Entities"),
+        ],
+        window: Duration::from_millis(5),
+        // Short enough that the readings are due every frame
+        interval: Duration::from_micros(1),
+        ..Default::default()
+    };
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default(), DiagnosticsPlugin))
+        .add_asset::<Font>()
+        .add_event::<WindowResized>()
+        .add_event::<WindowScaleFactorChanged>()
+        // Every frame takes a millisecond, so the number of frames in the window is steady
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(1)))
+        .insert_resource(ScreenDiagsRows::default().with("Entities", |world, text| {
+            write!(text, "{} entities", world.entities().len()).unwrap();
+        }))
+        .add_plugins((
+            ScreenDiagsPlugin::default()
+                .config(config)
+                // Bevy's font is there from the start, so the overlay is drawn without a window
+                .font(ScreenDiagsFont::Bevy)
+                .log_every(Duration::from_micros(1)),
+            ScreenDiagsUiPlugin,
+        ))
+        .add_systems(
+            Update,
+            (
+                start_counting
+                    .after(ScreenDiagsSet::Tick)
+                    .before(ScreenDiagsSet::Sample),
+                stop_counting.after(ScreenDiagsSet::Display),
+            ),
+        )
+        // Keep the systems on this thread, where the allocations are counted
+        .edit_schedule(Update, |schedule| {
+            schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        });
+
+    for _ in 0..WARM_UP_FRAMES {
+        app.update();
+    }
+    ALLOCATIONS.store(0, Ordering::Relaxed);
+    for _ in 0..COUNTED_FRAMES {
+        app.update();
+    }
+
+    assert_eq!(ALLOCATIONS.load(Ordering::Relaxed), 0);
+    // The values were written to the overlay, so its updates were counted too
+    let mut text_query = app.world.query_filtered::<&Text, With<ScreenDiagsText>>();
+    let mut written = |value| {
+        text_query
+            .iter(&app.world)
+            .flat_map(|text| &text.sections)
+            .any(|section| section.value.contains(value))
+    };
+    assert!(written("1.00 ms"));
+    assert!(written("entities"));
+}